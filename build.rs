@@ -0,0 +1,177 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PCIIDS: &str = "src/pciids/pci.ids";
+
+// One line of pci.ids with the lines indented below it.
+struct Entry {
+    id: u32,
+    name: String,
+    children: Vec<Entry>,
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={PCIIDS}");
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+
+    // The static tables make any embedded copy of the text redundant.
+    if cfg!(feature = "static-ids") {
+        write_tables(&out_dir);
+    } else {
+        #[cfg(feature = "compressed-ids")]
+        compress(&out_dir);
+    }
+}
+
+#[cfg(feature = "compressed-ids")]
+fn compress(out_dir: &Path) {
+    use flate2::Compression;
+    use flate2::write::DeflateEncoder;
+    use std::io::Write;
+
+    let content = fs::read(PCIIDS).unwrap();
+    let mut encoder = DeflateEncoder::new(vec![], Compression::best());
+    encoder.write_all(&content).unwrap();
+    fs::write(out_dir.join("pci.ids.deflate"), encoder.finish().unwrap()).unwrap();
+}
+
+fn write_tables(out_dir: &Path) {
+    let content = fs::read_to_string(PCIIDS).unwrap();
+    let (mut vendors, mut classes) = parse(&content);
+    sort(&mut vendors);
+    sort(&mut classes);
+
+    let mut out = String::new();
+    writeln!(out, "static DATABASE: Database = Database {{").unwrap();
+    writeln!(out, "vendors: Cow::Borrowed(&[").unwrap();
+    for vendor in &vendors {
+        write_vendor(&mut out, vendor);
+    }
+    writeln!(out, "]),").unwrap();
+    writeln!(out, "classes: Cow::Borrowed(&[").unwrap();
+    for class in &classes {
+        write_class(&mut out, class);
+    }
+    writeln!(out, "]),").unwrap();
+    writeln!(out, "}};").unwrap();
+
+    fs::write(out_dir.join("pci_ids.rs"), out).unwrap();
+}
+
+fn parse(content: &str) -> (Vec<Entry>, Vec<Entry>) {
+    let mut vendors: Vec<Entry> = vec![];
+    let mut classes: Vec<Entry> = vec![];
+    let mut in_classes = false;
+
+    for line in content.lines() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let depth = line.chars().take_while(|c| *c == '\t').count();
+        let mut text = &line[depth..];
+        if depth == 0 && text.starts_with("C ") {
+            in_classes = true;
+            text = &text[2..];
+        }
+
+        let (id, name) = text.split_once("  ").unwrap();
+        // Subsystems are keyed by "vendor device".
+        let id = id.split(' ').fold(0, |acc, part| {
+            (acc << 16) | u32::from_str_radix(part, 16).unwrap()
+        });
+        let entry = Entry {
+            id,
+            name: name.to_string(),
+            children: vec![],
+        };
+
+        let top = if in_classes {
+            &mut classes
+        } else {
+            &mut vendors
+        };
+        match depth {
+            0 => top.push(entry),
+            1 => top.last_mut().unwrap().children.push(entry),
+            _ => top
+                .last_mut()
+                .unwrap()
+                .children
+                .last_mut()
+                .unwrap()
+                .children
+                .push(entry),
+        }
+    }
+
+    (vendors, classes)
+}
+
+// Stable, so the first of duplicate IDs stays first as in the runtime parse.
+fn sort(entries: &mut [Entry]) {
+    entries.sort_by_key(|e| e.id);
+    for entry in entries {
+        sort(&mut entry.children);
+    }
+}
+
+fn write_vendor(out: &mut String, vendor: &Entry) {
+    writeln!(
+        out,
+        "Vendor {{ id: {:#06x}, name: Cow::Borrowed({:?}), devices: Cow::Borrowed(&[",
+        vendor.id, vendor.name
+    )
+    .unwrap();
+    for device in &vendor.children {
+        writeln!(
+            out,
+            "Device {{ id: {:#06x}, name: Cow::Borrowed({:?}), subsystems: Cow::Borrowed(&[",
+            device.id, device.name
+        )
+        .unwrap();
+        for sub in &device.children {
+            writeln!(
+                out,
+                "SubSystem {{ sub_vendor: {:#06x}, sub_device: {:#06x}, name: Cow::Borrowed({:?}) }},",
+                sub.id >> 16,
+                sub.id & 0xFFFF,
+                sub.name
+            )
+            .unwrap();
+        }
+        writeln!(out, "]) }},").unwrap();
+    }
+    writeln!(out, "]) }},").unwrap();
+}
+
+fn write_class(out: &mut String, class: &Entry) {
+    writeln!(
+        out,
+        "BaseClass {{ id: {:#04x}, name: Cow::Borrowed({:?}), sub_classes: Cow::Borrowed(&[",
+        class.id, class.name
+    )
+    .unwrap();
+    for sub_class in &class.children {
+        writeln!(
+            out,
+            "SubClass {{ id: {:#04x}, name: Cow::Borrowed({:?}), prog_ifs: Cow::Borrowed(&[",
+            sub_class.id, sub_class.name
+        )
+        .unwrap();
+        for prog_if in &sub_class.children {
+            writeln!(
+                out,
+                "ProgIf {{ id: {:#04x}, name: Cow::Borrowed({:?}) }},",
+                prog_if.id, prog_if.name
+            )
+            .unwrap();
+        }
+        writeln!(out, "]) }},").unwrap();
+    }
+    writeln!(out, "]) }},").unwrap();
+}
@@ -1,10 +1,22 @@
+use pci::aer::{CorrectableErrors, UncorrectableErrors};
+use pci::capability::{Capability, ExtendedCapability};
+use pci::enumerate::{self, PciDevice, Strategy};
 use pci::io_port::IoPort;
-use pci::{Method, PciConfig, ids};
+use pci::pcie::PciECapability;
+use pci::pm::PowerState;
+use pci::{
+    BridgeWindow, Method, PciAddress, PciCapability, PciConfig, PciExtendedCapability, dump, ids,
+    isolation,
+};
+use std::borrow::Cow;
 use std::env;
 
 #[cfg(target_family = "unix")]
 use pci::ecam::{self, Ecam};
 
+#[cfg(target_os = "linux")]
+use pci::sysfs::{self, Sysfs};
+
 #[cfg(target_family = "windows")]
 use pci::cfgmgr32::{self, Cfgmgr32};
 
@@ -13,11 +25,15 @@ struct Option {
     n: bool,
     nn: bool,
     v: bool,
+    groups: bool,
+    file: std::option::Option<String>,
+    ids: std::option::Option<String>,
 }
 
 fn main() {
     let mut option = Option::default();
-    for arg in env::args() {
+    let mut args = env::args();
+    while let Some(arg) = args.next() {
         match arg.as_str() {
             "-n" => {
                 option.n = true;
@@ -28,20 +44,48 @@ fn main() {
             "-v" => {
                 option.v = true;
             }
+            "-G" => {
+                option.groups = true;
+            }
+            "-F" => {
+                option.file = args.next();
+            }
+            "-i" => {
+                option.ids = args.next();
+            }
             _ => {}
         }
     }
 
+    if let Some(file) = &option.file {
+        let space = dump::load(file).unwrap();
+        let devices = space
+            .functions()
+            .filter_map(|address| {
+                pci::get_pci_config(space.get(address)).map(|v| PciDevice::new(address, v))
+            })
+            .collect();
+        print_devices(devices, &option);
+        return;
+    }
+
     #[cfg(target_family = "unix")]
     if ecam::support() {
-        let devices = scan_device::<Ecam>(0);
+        let devices = enumerate::enumerate::<Ecam>(0, Strategy::Bridges(&[0]));
+        print_devices(devices, &option);
+        return;
+    }
+
+    #[cfg(target_os = "linux")]
+    if sysfs::support() {
+        let devices = enumerate::enumerate::<Sysfs>(0, Strategy::Bridges(&[0]));
         print_devices(devices, &option);
         return;
     }
 
     #[cfg(target_family = "windows")]
     if cfgmgr32::support() {
-        let devices = scan_device::<Cfgmgr32>(0);
+        let devices = enumerate::enumerate::<Cfgmgr32>(0, Strategy::Bridges(&[0]));
         print_devices(devices, &option);
         return;
     }
@@ -50,91 +94,68 @@ fn main() {
     unsafe {
         libc::iopl(3)
     };
-    let devices = scan_device::<IoPort>(0);
+    let devices = enumerate::enumerate::<IoPort>(0, Strategy::Bridges(&[0]));
     print_devices(devices, &option);
 }
 
-fn scan_device<T: Method>(bus: u8) -> Vec<(u8, u8, u8, PciConfig<T>)> {
-    let mut devs = vec![];
-    let mut sub_buses = vec![];
-
-    for device in 0..32 {
-        let v = pci::get_pci_config(T::try_from(bus, device, 0).unwrap());
-        if let Some(v) = &v {
-            devs.push((bus, device, 0, v.clone()));
-
-            if let Some(t1) = v.get_type1_header() {
-                sub_buses.push(t1.secondary_bus_number());
-            }
-
-            if v.header_type().multi_function_device() {
-                for func in 1..8 {
-                    let v = pci::get_pci_config(T::try_from(bus, device, func).unwrap());
-                    if let Some(v) = &v {
-                        devs.push((bus, device, func, v.clone()));
-
-                        if let Some(t1) = v.get_type1_header() {
-                            sub_buses.push(t1.secondary_bus_number());
-                        }
-                    }
-                }
-            }
+fn print_devices<T: Method>(devices: Vec<PciDevice<T>>, option: &Option) {
+    if option.groups {
+        for (i, group) in isolation::isolation_groups(&devices).iter().enumerate() {
+            let addresses: Vec<String> = group.iter().map(|a| format!("{a:#}")).collect();
+            println!("Group {i}: {}", addresses.join(" "));
         }
+        return;
     }
 
-    for sub_bus in sub_buses {
-        let mut v = scan_device(sub_bus);
-        devs.append(&mut v);
-    }
-
-    devs
-}
-
-fn print_devices<T: Method>(mut devices: Vec<(u8, u8, u8, PciConfig<T>)>, option: &Option) {
-    devices.sort_by_key(|d| d.0);
+    let db = match &option.ids {
+        Some(path) => Cow::Owned(ids::Database::open(path).unwrap()),
+        None => ids::Database::load(),
+    };
 
-    for (bus, device, func, v) in devices {
-        print_device(bus, device, func, &v, option);
+    for device in devices {
+        print_device(device.address(), device.config(), &db, option);
     }
 }
 
-fn print_device<T: Method>(bus: u8, device: u8, func: u8, cfg: &PciConfig<T>, option: &Option) {
-    print!("{bus:02x}:{device:02x}.{func} ");
+fn print_device<T: Method>(
+    address: PciAddress,
+    cfg: &PciConfig<T>,
+    db: &ids::Database,
+    option: &Option,
+) {
+    print!("{address:#} ");
 
+    // Without any pci.ids the database is empty, so fall back to the numeric IDs.
     let ccode = cfg.class_code();
-    let base_class = ids::get_class(ccode.base_class()).unwrap();
-    match base_class.get_sub_class(ccode.sub_class()) {
-        Some(sub_class) => {
-            if option.n {
-                print!("{:02x}{:02x}: ", base_class.id(), sub_class.id());
-            } else {
-                print!("{}", sub_class.name());
-                if option.nn {
-                    print!(" [{:02x}{:02x}]", base_class.id(), sub_class.id());
-                }
-
-                print!(": ")
+    let (base_class, sub_class) = (ccode.base_class(), ccode.sub_class());
+    match db
+        .get_class(base_class)
+        .and_then(|c| c.get_sub_class(sub_class))
+    {
+        Some(name) if !option.n => {
+            print!("{}", name.name());
+            if option.nn {
+                print!(" [{base_class:02x}{sub_class:02x}]");
             }
+
+            print!(": ")
         }
         _ => {
-            print!("{:02x}{:02x}: ", base_class.id(), ccode.sub_class());
+            print!("{base_class:02x}{sub_class:02x}: ");
         }
     }
 
-    let vendor = ids::get_vendor(cfg.vendor_id()).unwrap();
-    match vendor.get_device(cfg.device_id()) {
-        Some(device) => {
-            if option.n {
-                print!("{:04x}:{:04x}", vendor.id(), device.id());
-            } else {
-                print!("{} {}", vendor.name(), device.name());
-                if option.nn {
-                    print!(" [{:04x}:{:04x}]", vendor.id(), device.id());
-                }
+    let (vendor_id, device_id) = (cfg.vendor_id(), cfg.device_id());
+    let vendor = db.get_vendor(vendor_id);
+    match vendor.zip(vendor.and_then(|v| v.get_device(device_id))) {
+        Some((vendor, device)) if !option.n => {
+            print!("{} {}", vendor.name(), device.name());
+            if option.nn {
+                print!(" [{vendor_id:04x}:{device_id:04x}]");
             }
         }
         _ => {
-            print!("{:04x}:{:04x}", vendor.id(), cfg.device_id());
+            print!("{vendor_id:04x}:{device_id:04x}");
         }
     }
 
@@ -198,28 +219,24 @@ fn print_device<T: Method>(bus: u8, device: u8, func: u8, cfg: &PciConfig<T>, op
     }
 
     if let Some(t0) = cfg.get_type0_header() {
-        if t0.subsystem_vendor_id() != 0 {
-            if let Some(device) = vendor.get_device(cfg.device_id()) {
-                if let Some(subsystem) =
-                    device.get_subsystem(t0.subsystem_vendor_id(), t0.subsystem_id())
-                {
+        let (sub_vendor_id, sub_id) = (t0.subsystem_vendor_id(), t0.subsystem_id());
+        if sub_vendor_id != 0 {
+            let subsystem = vendor
+                .and_then(|v| v.get_device(device_id))
+                .and_then(|d| d.get_subsystem(sub_vendor_id, sub_id));
+            match (vendor, subsystem, db.get_vendor(sub_vendor_id)) {
+                (Some(vendor), Some(subsystem), _) => {
                     println!("        Subsystem: {} {}", vendor.name(), subsystem.name());
-                } else {
-                    match ids::get_vendor(t0.subsystem_vendor_id()) {
-                        Some(subsystem) => {
-                            println!(
-                                "        Subsystem: {} Device {:04x}",
-                                subsystem.name(),
-                                t0.subsystem_id()
-                            );
-                        }
-                        _ => {
-                            println!("        Subsystem: {:04x}", t0.subsystem_vendor_id());
-                        }
-                    }
                 }
-            } else {
-                println!("        Subsystem: {} {}", vendor.name(), t0.subsystem_id());
+                (_, _, Some(sub_vendor)) => {
+                    println!(
+                        "        Subsystem: {} Device {sub_id:04x}",
+                        sub_vendor.name()
+                    );
+                }
+                _ => {
+                    println!("        Subsystem: {sub_vendor_id:04x}:{sub_id:04x}");
+                }
             }
         }
 
@@ -253,6 +270,97 @@ fn print_device<T: Method>(bus: u8, device: u8, func: u8, cfg: &PciConfig<T>, op
         }
     }
 
+    if let Some(t1) = cfg.get_type1_header() {
+        println!(
+            "        Bus: primary={:02x}, secondary={:02x}, subordinate={:02x}, sec-latency={}",
+            t1.primary_bus_number(),
+            t1.secondary_bus_number(),
+            t1.subordinate_bus_number(),
+            t1.secondary_latency_timer(),
+        );
+
+        print_window("I/O behind bridge", t1.io_window());
+        print_window("Memory behind bridge", t1.memory_window());
+        print_window(
+            "Prefetchable memory behind bridge",
+            t1.prefetchable_memory_window(),
+        );
+
+        let status = t1.secondary_status();
+        println!(
+            "        Secondary status: 66MHz{} FastB2B{} ParErr{} DEVSEL={} >TAbort{} <TAbort{} <MAbort{} <SERR{} <PERR{}",
+            flag(status.mhz_66_capable()),
+            flag(status.fast_back_to_back_transactions_capable()),
+            flag(status.master_data_parity_error()),
+            match status.devsel_timing() {
+                0 => "fast",
+                1 => "medium",
+                2 => "slow",
+                _ => "",
+            },
+            flag(status.signaled_target_abort()),
+            flag(status.received_target_abort()),
+            flag(status.received_master_abort()),
+            flag(status.received_system_error()),
+            flag(status.detected_parity_error()),
+        );
+
+        let ctl = t1.bridge_control();
+        println!(
+            "        BridgeCtl: Parity{} SERR{} NoISA{} VGA{} VGA16{} MAbort{} >Reset{} FastB2B{}",
+            flag(ctl.parity_error_response_enable()),
+            flag(ctl.serr_enable()),
+            flag(ctl.isa_enable()),
+            flag(ctl.vga_enable()),
+            flag(ctl.vga_16bit_decode()),
+            flag(ctl.master_abort_mode()),
+            flag(ctl.secondary_bus_reset()),
+            flag(ctl.fast_back_to_back_transactions_enable()),
+        );
+        println!(
+            "                PriDiscTmr{} SecDiscTmr{} DiscTmrStat{} DiscTmrSERREn{}",
+            flag(ctl.primary_discard_timeout()),
+            flag(ctl.secondary_discard_timeout()),
+            flag(ctl.discard_timer_status()),
+            flag(ctl.discard_timer_serr_enable()),
+        );
+    }
+
+    if let Some(t2) = cfg.get_type2_header() {
+        println!(
+            "        Bus: primary={:02x}, secondary={:02x}, subordinate={:02x}, sec-latency={}",
+            t2.pci_bus_number(),
+            t2.cardbus_bus_number(),
+            t2.subordinate_bus_number(),
+            t2.cardbus_latency_timer(),
+        );
+
+        print_window("Memory window 0", t2.memory_window0());
+        print_window("Memory window 1", t2.memory_window1());
+        print_window("I/O window 0", t2.io_window0());
+        print_window("I/O window 1", t2.io_window1());
+
+        let ctl = t2.bridge_control();
+        println!(
+            "        BridgeCtl: Parity{} SERR{} ISA{} VGA{} MAbort{} >Reset{} 16bInt{} PostWrite{}",
+            flag(ctl.parity_error_response_enable()),
+            flag(ctl.serr_enable()),
+            flag(ctl.isa_enable()),
+            flag(ctl.vga_enable()),
+            flag(ctl.master_abort_mode()),
+            flag(ctl.cardbus_reset()),
+            flag(ctl.interrupt_enable_16bit()),
+            flag(ctl.write_posting_enable()),
+        );
+
+        if t2.legacy_base() != 0 {
+            println!(
+                "        16-bit legacy interface ports at {:04x}",
+                t2.legacy_base()
+            );
+        }
+    }
+
     let rom = if let Some(t0) = cfg.get_type0_header() {
         Some(t0.expansion_rom())
     } else {
@@ -274,19 +382,421 @@ fn print_device<T: Method>(bus: u8, device: u8, func: u8, cfg: &PciConfig<T>, op
             }
 
             println!();
+
+            if option.v {
+                print_rom_images(cfg);
+            }
+        }
+    }
+
+    let mut caps = cfg.capabilities();
+    for cap in caps.by_ref() {
+        print_capability(cfg, &cap);
+    }
+    if let Some(e) = caps.error() {
+        println!("        Capabilities: <broken chain: {e:?}>");
+    }
+
+    let mut caps = cfg.extended_capabilities();
+    for cap in caps.by_ref() {
+        print_extended_capability(&cap);
+    }
+    if let Some(e) = caps.error() {
+        println!("        Capabilities: <broken extended chain: {e:?}>");
+    }
+}
+
+fn print_rom_images<T: Method>(cfg: &PciConfig<T>) {
+    let Ok(images) = cfg.rom_images() else {
+        return;
+    };
+
+    for image in images {
+        print!(
+            "                Image at {:06x}: {:?}, Vendor {:04x} Device {:04x}, Revision {:04x}, Length {}",
+            image.offset(),
+            image.code_type(),
+            image.vendor_id(),
+            image.device_id(),
+            image.code_revision(),
+            image.image_length()
+        );
+        if let Some(efi) = image.efi() {
+            print!(", {:?} {:?}", efi.subsystem(), efi.machine_type());
+        } else if !image.checksum_valid() {
+            print!(", <bad checksum>");
         }
+        println!();
     }
+}
 
-    if cfg.status().capabilities_list() {
-        let mut cap_next = cfg.capabilities_pointer();
-        let mut capability = cfg.capability();
-        while capability.is_some() {
-            let cap = capability.unwrap();
-            println!("        Capabilities: [{:02x}] {:?}", cap_next, cap.id());
-            cap_next = cap.next_pointer();
-            capability = cap.next();
+fn print_extended_capability<T: Method>(cap: &PciExtendedCapability<T>) {
+    print!(
+        "        Capabilities: [{:03x} v{}] ",
+        cap.offset(),
+        cap.version()
+    );
+    match cap.decode() {
+        ExtendedCapability::Aer(aer) => {
+            println!("Advanced Error Reporting");
+            print_uncorrectable("UESta", aer.uncorrectable_status());
+            print_uncorrectable("UEMsk", aer.uncorrectable_mask());
+            print_uncorrectable("UESvrt", aer.uncorrectable_severity());
+            print_correctable("CESta", aer.correctable_status());
+            print_correctable("CEMsk", aer.correctable_mask());
+
+            let ctl = aer.capabilities_control();
+            println!(
+                "                AERCap: First Error Pointer: {:02x}, ECRCGenCap{} ECRCGenEn{} ECRCChkCap{} ECRCChkEn{}",
+                ctl.first_error_pointer(),
+                flag(ctl.ecrc_generation_capable()),
+                flag(ctl.ecrc_generation_enable()),
+                flag(ctl.ecrc_check_capable()),
+                flag(ctl.ecrc_check_enable()),
+            );
+
+            let log = aer.header_log();
+            println!(
+                "                HeaderLog: {:08x} {:08x} {:08x} {:08x}",
+                log[0], log[1], log[2], log[3]
+            );
         }
+        ExtendedCapability::Acs(acs) => {
+            println!("Access Control Services");
+            let cap = acs.capability();
+            println!(
+                "                ACSCap: SrcValid{} TransBlk{} ReqRedir{} CmpltRedir{} UpstreamFwd{} EgressCtrl{} DirectTrans{}",
+                flag(cap.source_validation()),
+                flag(cap.translation_blocking()),
+                flag(cap.p2p_request_redirect()),
+                flag(cap.p2p_completion_redirect()),
+                flag(cap.upstream_forwarding()),
+                flag(cap.p2p_egress_control()),
+                flag(cap.direct_translated_p2p()),
+            );
+            let ctl = acs.control();
+            println!(
+                "                ACSCtl: SrcValid{} TransBlk{} ReqRedir{} CmpltRedir{} UpstreamFwd{} EgressCtrl{} DirectTrans{}",
+                flag(ctl.source_validation()),
+                flag(ctl.translation_blocking()),
+                flag(ctl.p2p_request_redirect()),
+                flag(ctl.p2p_completion_redirect()),
+                flag(ctl.upstream_forwarding()),
+                flag(ctl.p2p_egress_control()),
+                flag(ctl.direct_translated_p2p()),
+            );
+        }
+        ExtendedCapability::SrIov(sriov) => {
+            println!("Single Root I/O Virtualization (SR-IOV)");
+            println!(
+                "                IOVCtl: Enable{} Migration{} Interrupt{} MSE{} ARIHierarchy{}",
+                flag(sriov.control().vf_enable()),
+                flag(sriov.control().vf_migration_enable()),
+                flag(sriov.control().vf_migration_interrupt_enable()),
+                flag(sriov.control().vf_memory_space_enable()),
+                flag(sriov.control().ari_capable_hierarchy()),
+            );
+            println!(
+                "                Initial VFs: {}, Total VFs: {}, Number of VFs: {}",
+                sriov.initial_vfs(),
+                sriov.total_vfs(),
+                sriov.num_vfs()
+            );
+            println!(
+                "                VF offset: {}, stride: {}, Device ID: {:04x}",
+                sriov.first_vf_offset(),
+                sriov.vf_stride(),
+                sriov.vf_device_id()
+            );
+        }
+        _ => match cap.id() {
+            Some(id) => println!("{id:?}"),
+            None => println!("Extended Capability ID {:#06x}", cap.raw_id()),
+        },
+    }
+}
+
+fn print_uncorrectable(name: &str, e: UncorrectableErrors) {
+    println!(
+        "                {name}: DLP{} SDES{} TLP{} FCP{} CmpltTO{} CmpltAbrt{} UnxCmplt{} RxOF{} MalfTLP{} ECRC{} UnsupReq{} ACSViol{}",
+        flag(e.data_link_protocol_error()),
+        flag(e.surprise_down_error()),
+        flag(e.poisoned_tlp_received()),
+        flag(e.flow_control_protocol_error()),
+        flag(e.completion_timeout()),
+        flag(e.completer_abort()),
+        flag(e.unexpected_completion()),
+        flag(e.receiver_overflow()),
+        flag(e.malformed_tlp()),
+        flag(e.ecrc_error()),
+        flag(e.unsupported_request_error()),
+        flag(e.acs_violation()),
+    );
+}
+
+fn print_correctable(name: &str, e: CorrectableErrors) {
+    println!(
+        "                {name}: RxErr{} BadTLP{} BadDLLP{} Rollover{} Timeout{} AdvNonFatalErr{}",
+        flag(e.receiver_error()),
+        flag(e.bad_tlp()),
+        flag(e.bad_dllp()),
+        flag(e.replay_num_rollover()),
+        flag(e.replay_timer_timeout()),
+        flag(e.advisory_non_fatal_error()),
+    );
+}
+
+fn print_capability<T: Method>(cfg: &PciConfig<T>, cap: &PciCapability<T>) {
+    print!("        Capabilities: [{:02x}] ", cap.offset());
+    match cap.decode() {
+        Capability::Pm(pm) => {
+            println!("Power Management version {}", pm.version());
+
+            let pmc = pm.capabilities();
+            println!(
+                "                Flags: PMEClk{} DSI{} D1{} D2{} AuxCurrent={}mA PME(D0{},D1{},D2{},D3hot{},D3cold{})",
+                flag(pmc.pme_clock()),
+                flag(pmc.device_specific_initialization()),
+                flag(pmc.d1_support()),
+                flag(pmc.d2_support()),
+                pmc.aux_current(),
+                flag(pmc.pme_support(PowerState::D0)),
+                flag(pmc.pme_support(PowerState::D1)),
+                flag(pmc.pme_support(PowerState::D2)),
+                flag(pmc.pme_support(PowerState::D3Hot)),
+                flag(pmc.pme_support_d3cold()),
+            );
+
+            let pmcsr = pm.control_status();
+            println!(
+                "                Status: {:?} NoSoftRst{} PME-Enable{} DSel={} DScale={} PME{}",
+                pmcsr.power_state(),
+                flag(pmcsr.no_soft_reset()),
+                flag(pmcsr.pme_enable()),
+                pmcsr.data_select(),
+                pmcsr.data_scale(),
+                flag(pmcsr.pme_status()),
+            );
+        }
+        Capability::Msi(msi) => {
+            let ctl = msi.message_control();
+            println!(
+                "MSI: Enable{} Count={}/{} Maskable{} 64bit{}",
+                flag(ctl.enable()),
+                ctl.multiple_message_enable(),
+                ctl.multiple_message_capable(),
+                flag(ctl.per_vector_masking()),
+                flag(ctl.is_64bit()),
+            );
+            println!(
+                "                Address: {:016x}  Data: {:04x}",
+                msi.message_address(),
+                msi.message_data()
+            );
+            if let (Some(mask), Some(pending)) = (msi.mask_bits(), msi.pending_bits()) {
+                println!("                Masking: {mask:08x}  Pending: {pending:08x}");
+            }
+        }
+        Capability::VendorSpecific(vs) => {
+            println!("Vendor Specific Information: Len={:02x}", vs.length())
+        }
+        Capability::PciBridgeSubVendorId(ssvid) => println!(
+            "Subsystem: {:04x}:{:04x}",
+            ssvid.subsystem_vendor_id(),
+            ssvid.subsystem_id()
+        ),
+        Capability::PciE(pcie) => print_pcie(&pcie),
+        Capability::MsiX(msix) => {
+            let ctl = msix.message_control();
+            println!(
+                "MSI-X: Enable{} Count={} Masked{}",
+                flag(ctl.enable()),
+                ctl.table_size(),
+                flag(ctl.function_mask()),
+            );
+            let t0 = cfg.get_type0_header();
+            print_msix_location(
+                "Vector table",
+                msix.table_bir(),
+                msix.table_offset(),
+                t0.as_ref().and_then(|t0| msix.table_address(t0)),
+            );
+            print_msix_location(
+                "PBA",
+                msix.pba_bir(),
+                msix.pba_offset(),
+                t0.as_ref().and_then(|t0| msix.pba_address(t0)),
+            );
+        }
+        Capability::Unknown(raw) => println!("#{:02x}", raw.id()),
+        _ => println!("{:?}", cap.id()),
+    }
+}
+
+fn print_msix_location(name: &str, bir: u8, offset: u32, address: std::option::Option<u64>) {
+    print!("                {name}: BAR={bir} offset={offset:08x}");
+    if let Some(address) = address {
+        print!(" at {address:016x}");
+    }
+    println!();
+}
+
+fn print_pcie<T: Method>(pcie: &PciECapability<T>) {
+    println!(
+        "Express (v{}) {:?}, MSI {:02x}",
+        pcie.version(),
+        pcie.device_port_type(),
+        pcie.capabilities().interrupt_message_number()
+    );
+
+    let devcap = pcie.device_capabilities();
+    println!(
+        "                DevCap: MaxPayload {} bytes, PhantFunc {}, ExtTag{} RBE{} FLReset{}",
+        devcap.max_payload_size_supported(),
+        devcap.phantom_functions(),
+        flag(devcap.extended_tag_field()),
+        flag(devcap.role_based_error_reporting()),
+        flag(devcap.function_level_reset_capable()),
+    );
+
+    let devctl = pcie.device_control();
+    println!(
+        "                DevCtl: CorrErr{} NonFatalErr{} FatalErr{} UnsupReq{} RlxdOrd{} ExtTag{} NoSnoop{}",
+        flag(devctl.correctable_error_reporting_enable()),
+        flag(devctl.non_fatal_error_reporting_enable()),
+        flag(devctl.fatal_error_reporting_enable()),
+        flag(devctl.unsupported_request_reporting_enable()),
+        flag(devctl.relaxed_ordering_enable()),
+        flag(devctl.extended_tag_field_enable()),
+        flag(devctl.no_snoop_enable()),
+    );
+    println!(
+        "                        MaxPayload {} bytes, MaxReadReq {} bytes",
+        devctl.max_payload_size(),
+        devctl.max_read_request_size()
+    );
+
+    let devsta = pcie.device_status();
+    println!(
+        "                DevSta: CorrErr{} NonFatalErr{} FatalErr{} UnsupReq{} AuxPwr{} TransPend{}",
+        flag(devsta.correctable_error_detected()),
+        flag(devsta.non_fatal_error_detected()),
+        flag(devsta.fatal_error_detected()),
+        flag(devsta.unsupported_request_detected()),
+        flag(devsta.aux_power_detected()),
+        flag(devsta.transactions_pending()),
+    );
+
+    let lnkcap = pcie.link_capabilities();
+    println!(
+        "                LnkCap: Port #{}, Speed {:?}, Width x{}, ASPM {:?}",
+        lnkcap.port_number(),
+        lnkcap.max_link_speed(),
+        lnkcap.max_link_width(),
+        lnkcap.aspm_support()
+    );
+
+    let lnkctl = pcie.link_control();
+    println!(
+        "                LnkCtl: ASPM {:?}; Disabled{} CommClk{} ExtSynch{} ClockPM{}",
+        lnkctl.aspm_control(),
+        flag(lnkctl.link_disable()),
+        flag(lnkctl.common_clock_configuration()),
+        flag(lnkctl.extended_synch()),
+        flag(lnkctl.enable_clock_power_management()),
+    );
+
+    let lnksta = pcie.link_status();
+    println!(
+        "                LnkSta: Speed {:?}, Width x{}, Train{} SlotClk{} DLActive{}",
+        lnksta.current_link_speed(),
+        lnksta.negotiated_link_width(),
+        flag(lnksta.link_training()),
+        flag(lnksta.slot_clock_configuration()),
+        flag(lnksta.data_link_layer_link_active()),
+    );
+
+    if let Some(sltcap) = pcie.slot_capabilities() {
+        println!(
+            "                SltCap: AttnBtn{} PwrCtrl{} MRL{} AttnInd{} PwrInd{} HotPlug{} Surprise{} Slot #{}",
+            flag(sltcap.attention_button_present()),
+            flag(sltcap.power_controller_present()),
+            flag(sltcap.mrl_sensor_present()),
+            flag(sltcap.attention_indicator_present()),
+            flag(sltcap.power_indicator_present()),
+            flag(sltcap.hot_plug_capable()),
+            flag(sltcap.hot_plug_surprise()),
+            sltcap.physical_slot_number(),
+        );
+    }
+
+    if let Some(sltsta) = pcie.slot_status() {
+        println!(
+            "                SltSta: AttnBtn{} PowerFlt{} MRL{} CmdCplt{} PresDet{} Interlock{}",
+            flag(sltsta.attention_button_pressed()),
+            flag(sltsta.power_fault_detected()),
+            flag(sltsta.mrl_sensor_changed()),
+            flag(sltsta.command_completed()),
+            flag(sltsta.presence_detect_state()),
+            flag(sltsta.electromechanical_interlock_status()),
+        );
+    }
+
+    if let Some(rootctl) = pcie.root_control() {
+        println!(
+            "                RootCtl: ErrCorrectable{} ErrNon-Fatal{} ErrFatal{} PMEIntEna{} CRSVisible{}",
+            flag(rootctl.system_error_on_correctable_error_enable()),
+            flag(rootctl.system_error_on_non_fatal_error_enable()),
+            flag(rootctl.system_error_on_fatal_error_enable()),
+            flag(rootctl.pme_interrupt_enable()),
+            flag(rootctl.crs_software_visibility_enable()),
+        );
+    }
+
+    if let Some(devcap2) = pcie.device_capabilities2() {
+        println!(
+            "                DevCap2: Completion Timeout: {:x}, TimeoutDis{} ARIFwd{} LTR{} 10BitTagReq{}",
+            devcap2.completion_timeout_ranges(),
+            flag(devcap2.completion_timeout_disable_supported()),
+            flag(devcap2.ari_forwarding_supported()),
+            flag(devcap2.ltr_mechanism_supported()),
+            flag(devcap2.tag_10bit_requester_supported()),
+        );
+    }
+
+    if let Some(lnksta2) = pcie.link_status2() {
+        println!(
+            "                LnkSta2: EqualizationComplete{} EqualizationPhase1{} EqualizationPhase2{} EqualizationPhase3{}",
+            flag(lnksta2.equalization_complete()),
+            flag(lnksta2.equalization_phase1_successful()),
+            flag(lnksta2.equalization_phase2_successful()),
+            flag(lnksta2.equalization_phase3_successful()),
+        );
+    }
+}
+
+fn print_window(name: &str, window: BridgeWindow) {
+    if window.enabled() {
+        println!(
+            "        {name}: {:08x}-{:08x} [size={}]",
+            window.base(),
+            window.limit(),
+            size(window.size())
+        );
+    } else {
+        println!("        {name}: [disabled]");
+    }
+}
+
+fn size(value: u64) -> String {
+    let units = ["", "K", "M", "G", "T"];
+    let mut value = value;
+    let mut unit = 0;
+    while value >= 1024 && value % 1024 == 0 && unit < units.len() - 1 {
+        value /= 1024;
+        unit += 1;
     }
+    format!("{value}{}", units[unit])
 }
 
 fn flag(f: bool) -> &'static str {
@@ -0,0 +1,233 @@
+use super::error;
+use super::{Method, PciExtendedCapability};
+
+pub const OFFSET_CAPABILITY: u16 = 0x04;
+pub const OFFSET_CONTROL: u16 = 0x06;
+pub const OFFSET_EGRESS_CONTROL_VECTOR: u16 = 0x08;
+
+pub const SOURCE_VALIDATION: u16 = 0x0001;
+pub const TRANSLATION_BLOCKING: u16 = 0x0002;
+pub const P2P_REQUEST_REDIRECT: u16 = 0x0004;
+pub const P2P_COMPLETION_REDIRECT: u16 = 0x0008;
+pub const UPSTREAM_FORWARDING: u16 = 0x0010;
+pub const P2P_EGRESS_CONTROL: u16 = 0x0020;
+pub const DIRECT_TRANSLATED_P2P: u16 = 0x0040;
+
+// What a port must enforce before the functions below it count as isolated.
+pub const ISOLATION_FLAGS: u16 =
+    SOURCE_VALIDATION | P2P_REQUEST_REDIRECT | P2P_COMPLETION_REDIRECT | UPSTREAM_FORWARDING;
+
+#[derive(Clone, Debug)]
+pub struct AcsCapability<T: Method> {
+    pub(crate) raw: PciExtendedCapability<T>,
+    capability: AcsCapabilities,
+    control: AcsControl,
+    egress_control_vector: Vec<u32>,
+}
+
+impl<T: Method> AcsCapability<T> {
+    pub(crate) fn new(raw: PciExtendedCapability<T>) -> Self {
+        let capability = AcsCapabilities(raw.read16(OFFSET_CAPABILITY));
+
+        let mut egress_control_vector = vec![];
+        if capability.p2p_egress_control() {
+            let dwords = capability.egress_control_vector_size().div_ceil(32);
+            for i in 0..dwords {
+                egress_control_vector.push(raw.read32(OFFSET_EGRESS_CONTROL_VECTOR + i * 4));
+            }
+        }
+
+        AcsCapability {
+            capability,
+            control: AcsControl(raw.read16(OFFSET_CONTROL)),
+            egress_control_vector,
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &PciExtendedCapability<T> {
+        &self.raw
+    }
+
+    pub fn capability(&self) -> AcsCapabilities {
+        self.capability
+    }
+
+    pub fn control(&self) -> AcsControl {
+        self.control
+    }
+
+    // Bit n set blocks peer-to-peer requests towards function or port n.
+    pub fn egress_control_vector(&self) -> &[u32] {
+        &self.egress_control_vector
+    }
+
+    // Whether every flag out of `flags` that the function implements is enabled.
+    // Egress control is never masked off, so asking for it fails when it isn't implemented.
+    pub fn enabled(&self, flags: u16) -> bool {
+        let flags = flags & (self.capability.0 | P2P_EGRESS_CONTROL);
+        (self.control.0 & flags) == flags
+    }
+
+    pub fn set_control(&self, control: u16) -> Result<(), error::Error> {
+        self.raw.write16(OFFSET_CONTROL, control)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AcsCapabilities(u16);
+
+impl AcsCapabilities {
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    pub fn source_validation(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn translation_blocking(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn p2p_request_redirect(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn p2p_completion_redirect(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn upstream_forwarding(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn p2p_egress_control(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn direct_translated_p2p(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn enhanced_capability(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    // An encoded size of 0 means 256 bits.
+    pub fn egress_control_vector_size(&self) -> u16 {
+        match self.0 >> 8 {
+            0 => 256,
+            n => n,
+        }
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AcsControl(u16);
+
+impl AcsControl {
+    pub fn bits(&self) -> u16 {
+        self.0
+    }
+
+    pub fn source_validation(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn translation_blocking(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn p2p_request_redirect(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn p2p_completion_redirect(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn upstream_forwarding(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn p2p_egress_control(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn direct_translated_p2p(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn io_request_blocking(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    pub fn dsp_memory_target_access(&self) -> u8 {
+        ((self.0 >> 8) & 0x03) as u8
+    }
+
+    pub fn usp_memory_target_access(&self) -> u8 {
+        ((self.0 >> 10) & 0x03) as u8
+    }
+
+    pub fn unclaimed_request_redirect(&self) -> bool {
+        self.get_bool(12)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::ExtendedCapability;
+    use crate::testing::config_with_ext_cap;
+
+    #[test]
+    fn decode() {
+        let body = [0x3F, 0x20, 0x1D, 0x00, 0x06, 0x00, 0x00, 0x00];
+        let cfg = config_with_ext_cap(0x000D, 1, &body);
+        let Some(ExtendedCapability::Acs(acs)) =
+            cfg.extended_capabilities().next().map(|c| c.decode())
+        else {
+            panic!("expected an ACS capability");
+        };
+
+        let cap = acs.capability();
+        assert!(cap.source_validation());
+        assert!(cap.translation_blocking());
+        assert!(cap.p2p_request_redirect());
+        assert!(cap.p2p_egress_control());
+        assert!(!cap.direct_translated_p2p());
+        assert_eq!(cap.egress_control_vector_size(), 32);
+        assert_eq!(acs.egress_control_vector(), &[0x06]);
+
+        let ctl = acs.control();
+        assert!(ctl.source_validation());
+        assert!(!ctl.translation_blocking());
+        assert!(ctl.upstream_forwarding());
+        assert!(!ctl.p2p_egress_control());
+
+        // Unimplemented flags are ignored, except for egress control.
+        assert!(acs.enabled(ISOLATION_FLAGS));
+        assert!(!acs.enabled(ISOLATION_FLAGS | P2P_EGRESS_CONTROL));
+        assert!(!acs.enabled(TRANSLATION_BLOCKING));
+        assert!(acs.enabled(DIRECT_TRANSLATED_P2P));
+
+        let cfg = config_with_ext_cap(0x000D, 1, &[0x1F, 0x00, 0x1D, 0x00]);
+        let acs = AcsCapability::new(cfg.extended_capabilities().next().unwrap());
+        assert!(!acs.enabled(P2P_EGRESS_CONTROL));
+
+        acs.set_control(0x003F).unwrap();
+        assert_eq!(acs.raw().read16(OFFSET_CONTROL), 0x003F);
+    }
+}
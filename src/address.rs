@@ -0,0 +1,197 @@
+use super::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+pub const MAX_DEVICE: u8 = 31;
+pub const MAX_FUNCTION: u8 = 7;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PciAddress {
+    domain: u16,
+    bus: u8,
+    device: u8,
+    function: u8,
+}
+
+impl PciAddress {
+    pub fn new(domain: u16, bus: u8, device: u8, function: u8) -> Result<Self, Error> {
+        if device > MAX_DEVICE {
+            return Err(Error::InvalidAddress(format!("device {device:02x}")));
+        }
+
+        if function > MAX_FUNCTION {
+            return Err(Error::InvalidAddress(format!("function {function:x}")));
+        }
+
+        Ok(PciAddress {
+            domain,
+            bus,
+            device,
+            function,
+        })
+    }
+
+    // With ARI the device number is always 0 and the 8-bit function number takes
+    // the place of `devfn`.
+    pub fn new_ari(domain: u16, bus: u8, function: u8) -> Self {
+        PciAddress::from_devfn(domain, bus, function)
+    }
+
+    pub fn from_devfn(domain: u16, bus: u8, devfn: u8) -> Self {
+        PciAddress {
+            domain,
+            bus,
+            device: devfn >> 3,
+            function: devfn & 0x07,
+        }
+    }
+
+    pub fn from_routing_id(domain: u16, routing_id: u16) -> Self {
+        PciAddress::from_devfn(domain, (routing_id >> 8) as u8, routing_id as u8)
+    }
+
+    pub fn domain(&self) -> u16 {
+        self.domain
+    }
+
+    pub fn bus(&self) -> u8 {
+        self.bus
+    }
+
+    pub fn device(&self) -> u8 {
+        self.device
+    }
+
+    pub fn function(&self) -> u8 {
+        self.function
+    }
+
+    pub fn devfn(&self) -> u8 {
+        (self.device << 3) | self.function
+    }
+
+    pub fn ari_function(&self) -> u8 {
+        self.devfn()
+    }
+
+    pub fn routing_id(&self) -> u16 {
+        ((self.bus as u16) << 8) | self.devfn() as u16
+    }
+}
+
+impl fmt::Display for PciAddress {
+    // `{:#}` omits the domain like lspci does without `-D`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !f.alternate() {
+            write!(f, "{:04x}:", self.domain)?;
+        }
+
+        write!(
+            f,
+            "{:02x}:{:02x}.{:x}",
+            self.bus, self.device, self.function
+        )
+    }
+}
+
+impl FromStr for PciAddress {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidAddress(s.to_string());
+
+        let (rest, function) = s.rsplit_once('.').ok_or_else(invalid)?;
+        let mut parts: Vec<&str> = rest.split(':').collect();
+        let device = parts.pop().ok_or_else(invalid)?;
+        let bus = parts.pop().ok_or_else(invalid)?;
+        let domain = match parts.as_slice() {
+            [] => "0",
+            [domain] => domain,
+            _ => return Err(invalid()),
+        };
+
+        let domain = parse_hex(domain, 4).ok_or_else(invalid)?;
+        let bus = parse_hex(bus, 2).ok_or_else(invalid)?;
+        let device = parse_hex(device, 2).ok_or_else(invalid)?;
+
+        let function = parse_hex(function, 1).ok_or_else(invalid)?;
+        PciAddress::new(domain, bus as u8, device as u8, function as u8)
+    }
+}
+
+fn parse_hex(value: &str, max_len: usize) -> Option<u16> {
+    if value.is_empty() || value.len() > max_len {
+        return None;
+    }
+
+    if !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    u16::from_str_radix(value, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format() {
+        let addr: PciAddress = "0001:3a:1f.7".parse().unwrap();
+        assert_eq!(addr, PciAddress::new(1, 0x3A, 0x1F, 7).unwrap());
+        assert_eq!(addr.to_string(), "0001:3a:1f.7");
+        assert_eq!(format!("{addr:#}"), "3a:1f.7");
+        assert_eq!(addr.routing_id(), 0x3AFF);
+
+        let addr: PciAddress = "00:02.0".parse().unwrap();
+        assert_eq!(addr, PciAddress::new(0, 0, 2, 0).unwrap());
+
+        assert!("00:20.0".parse::<PciAddress>().is_err());
+        assert!("00:1f.8".parse::<PciAddress>().is_err());
+        assert!("00:1f".parse::<PciAddress>().is_err());
+        assert!("0:0:00:1f.0".parse::<PciAddress>().is_err());
+        assert!("100:1f.0".parse::<PciAddress>().is_err());
+        assert!("+0:1f.0".parse::<PciAddress>().is_err());
+    }
+
+    #[test]
+    fn ari_function() {
+        let addr = PciAddress::from_devfn(0, 0x81, 0x92);
+        assert_eq!(addr.device(), 0x12);
+        assert_eq!(addr.function(), 2);
+        assert_eq!(addr.ari_function(), 0x92);
+        assert_eq!(PciAddress::from_routing_id(0, 0x8192), addr);
+        assert_eq!(PciAddress::new_ari(0, 0x81, 0x92), addr);
+
+        assert_eq!(addr.to_string(), "0000:81:12.2");
+        assert!("81:00.92".parse::<PciAddress>().is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        for s in [
+            "0000:00:00.0",
+            "0001:3a:1f.7",
+            "0002:81:12.2",
+            "ffff:ff:1f.7",
+        ] {
+            assert_eq!(s.parse::<PciAddress>().unwrap().to_string(), s);
+        }
+
+        let addr = PciAddress::new_ari(2, 0x81, 0x92);
+        assert_eq!(addr.to_string().parse::<PciAddress>().unwrap(), addr);
+    }
+
+    #[test]
+    fn ordering() {
+        let mut addrs = [
+            PciAddress::new(1, 0, 0, 0).unwrap(),
+            PciAddress::new(0, 1, 0, 0).unwrap(),
+            PciAddress::new(0, 0, 1, 0).unwrap(),
+            PciAddress::new(0, 0, 0, 1).unwrap(),
+        ];
+        addrs.sort();
+        assert_eq!(addrs[0], PciAddress::new(0, 0, 0, 1).unwrap());
+        assert_eq!(addrs[3], PciAddress::new(1, 0, 0, 0).unwrap());
+    }
+}
@@ -0,0 +1,489 @@
+use super::error;
+use super::pcie::DevicePortType;
+use super::{Method, PciExtendedCapability};
+
+pub const OFFSET_UNCORRECTABLE_STATUS: u16 = 0x04;
+pub const OFFSET_UNCORRECTABLE_MASK: u16 = 0x08;
+pub const OFFSET_UNCORRECTABLE_SEVERITY: u16 = 0x0C;
+pub const OFFSET_CORRECTABLE_STATUS: u16 = 0x10;
+pub const OFFSET_CORRECTABLE_MASK: u16 = 0x14;
+pub const OFFSET_CAPABILITIES_CONTROL: u16 = 0x18;
+pub const OFFSET_HEADER_LOG: u16 = 0x1C;
+pub const OFFSET_ROOT_ERROR_COMMAND: u16 = 0x2C;
+pub const OFFSET_ROOT_ERROR_STATUS: u16 = 0x30;
+pub const OFFSET_ERROR_SOURCE_ID: u16 = 0x34;
+
+#[derive(Clone, Debug)]
+pub struct AerCapability<T: Method> {
+    pub(crate) raw: PciExtendedCapability<T>,
+    uncorrectable_status: UncorrectableErrors,
+    uncorrectable_mask: UncorrectableErrors,
+    uncorrectable_severity: UncorrectableErrors,
+    correctable_status: CorrectableErrors,
+    correctable_mask: CorrectableErrors,
+    capabilities_control: AerCapabilitiesControl,
+    header_log: [u32; 4],
+    root_error_command: Option<RootErrorCommand>,
+    root_error_status: Option<RootErrorStatus>,
+    error_source_id: Option<u32>,
+}
+
+impl<T: Method> AerCapability<T> {
+    pub(crate) fn new(raw: PciExtendedCapability<T>) -> Self {
+        let mut header_log = [0; 4];
+        for (i, dw) in header_log.iter_mut().enumerate() {
+            *dw = raw.read32(OFFSET_HEADER_LOG + i as u16 * 4);
+        }
+
+        // Other functions end the capability before the root registers.
+        let root = matches!(
+            raw.port_type(),
+            Some(DevicePortType::RootPort | DevicePortType::RootComplexEventCollector)
+        );
+        let root_read32 = |offset: u16| root.then(|| raw.read32(offset));
+
+        AerCapability {
+            uncorrectable_status: UncorrectableErrors(raw.read32(OFFSET_UNCORRECTABLE_STATUS)),
+            uncorrectable_mask: UncorrectableErrors(raw.read32(OFFSET_UNCORRECTABLE_MASK)),
+            uncorrectable_severity: UncorrectableErrors(raw.read32(OFFSET_UNCORRECTABLE_SEVERITY)),
+            correctable_status: CorrectableErrors(raw.read32(OFFSET_CORRECTABLE_STATUS)),
+            correctable_mask: CorrectableErrors(raw.read32(OFFSET_CORRECTABLE_MASK)),
+            capabilities_control: AerCapabilitiesControl(raw.read32(OFFSET_CAPABILITIES_CONTROL)),
+            header_log,
+            root_error_command: root_read32(OFFSET_ROOT_ERROR_COMMAND).map(RootErrorCommand),
+            root_error_status: root_read32(OFFSET_ROOT_ERROR_STATUS).map(RootErrorStatus),
+            error_source_id: root_read32(OFFSET_ERROR_SOURCE_ID),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &PciExtendedCapability<T> {
+        &self.raw
+    }
+
+    pub fn uncorrectable_status(&self) -> UncorrectableErrors {
+        self.uncorrectable_status
+    }
+
+    pub fn uncorrectable_mask(&self) -> UncorrectableErrors {
+        self.uncorrectable_mask
+    }
+
+    // Set bits are reported as fatal, clear bits as non-fatal.
+    pub fn uncorrectable_severity(&self) -> UncorrectableErrors {
+        self.uncorrectable_severity
+    }
+
+    pub fn correctable_status(&self) -> CorrectableErrors {
+        self.correctable_status
+    }
+
+    pub fn correctable_mask(&self) -> CorrectableErrors {
+        self.correctable_mask
+    }
+
+    pub fn capabilities_control(&self) -> AerCapabilitiesControl {
+        self.capabilities_control
+    }
+
+    pub fn first_error_pointer(&self) -> u8 {
+        self.capabilities_control.first_error_pointer()
+    }
+
+    pub fn header_log(&self) -> [u32; 4] {
+        self.header_log
+    }
+
+    // The root registers are only implemented by root ports and event collectors.
+    pub fn root_error_command(&self) -> Option<RootErrorCommand> {
+        self.root_error_command
+    }
+
+    pub fn root_error_status(&self) -> Option<RootErrorStatus> {
+        self.root_error_status
+    }
+
+    pub fn correctable_source_id(&self) -> Option<u16> {
+        self.error_source_id.map(|id| id as u16)
+    }
+
+    pub fn uncorrectable_source_id(&self) -> Option<u16> {
+        self.error_source_id.map(|id| (id >> 16) as u16)
+    }
+
+    // The status registers are RW1C, so only the given bits are cleared.
+    pub fn clear_uncorrectable_status(
+        &self,
+        errors: UncorrectableErrors,
+    ) -> Result<(), error::Error> {
+        self.raw.write32(OFFSET_UNCORRECTABLE_STATUS, errors.0)
+    }
+
+    pub fn clear_correctable_status(&self, errors: CorrectableErrors) -> Result<(), error::Error> {
+        self.raw.write32(OFFSET_CORRECTABLE_STATUS, errors.0)
+    }
+
+    pub fn clear_root_error_status(&self, status: RootErrorStatus) -> Result<(), error::Error> {
+        if self.root_error_status.is_none() {
+            return Err(error::Error::OutOfRange(
+                self.raw.offset() + OFFSET_ROOT_ERROR_STATUS,
+            ));
+        }
+
+        // The interrupt message number is read-only.
+        self.raw
+            .write32(OFFSET_ROOT_ERROR_STATUS, status.0 & 0x0000_007F)
+    }
+
+    // Clears everything latched when this capability was decoded.
+    pub fn clear(&self) -> Result<(), error::Error> {
+        self.clear_uncorrectable_status(self.uncorrectable_status)?;
+        self.clear_correctable_status(self.correctable_status)?;
+        match self.root_error_status {
+            Some(status) => self.clear_root_error_status(status),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UncorrectableErrors(u32);
+
+impl UncorrectableErrors {
+    pub fn from(value: u32) -> Self {
+        UncorrectableErrors(value)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn data_link_protocol_error(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn surprise_down_error(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn poisoned_tlp_received(&self) -> bool {
+        self.get_bool(12)
+    }
+
+    pub fn flow_control_protocol_error(&self) -> bool {
+        self.get_bool(13)
+    }
+
+    pub fn completion_timeout(&self) -> bool {
+        self.get_bool(14)
+    }
+
+    pub fn completer_abort(&self) -> bool {
+        self.get_bool(15)
+    }
+
+    pub fn unexpected_completion(&self) -> bool {
+        self.get_bool(16)
+    }
+
+    pub fn receiver_overflow(&self) -> bool {
+        self.get_bool(17)
+    }
+
+    pub fn malformed_tlp(&self) -> bool {
+        self.get_bool(18)
+    }
+
+    pub fn ecrc_error(&self) -> bool {
+        self.get_bool(19)
+    }
+
+    pub fn unsupported_request_error(&self) -> bool {
+        self.get_bool(20)
+    }
+
+    pub fn acs_violation(&self) -> bool {
+        self.get_bool(21)
+    }
+
+    pub fn uncorrectable_internal_error(&self) -> bool {
+        self.get_bool(22)
+    }
+
+    pub fn mc_blocked_tlp(&self) -> bool {
+        self.get_bool(23)
+    }
+
+    pub fn atomic_op_egress_blocked(&self) -> bool {
+        self.get_bool(24)
+    }
+
+    pub fn tlp_prefix_blocked_error(&self) -> bool {
+        self.get_bool(25)
+    }
+
+    pub fn poisoned_tlp_egress_blocked(&self) -> bool {
+        self.get_bool(26)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u32 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CorrectableErrors(u32);
+
+impl CorrectableErrors {
+    pub fn from(value: u32) -> Self {
+        CorrectableErrors(value)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn receiver_error(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn bad_tlp(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn bad_dllp(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    pub fn replay_num_rollover(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    pub fn replay_timer_timeout(&self) -> bool {
+        self.get_bool(12)
+    }
+
+    pub fn advisory_non_fatal_error(&self) -> bool {
+        self.get_bool(13)
+    }
+
+    pub fn corrected_internal_error(&self) -> bool {
+        self.get_bool(14)
+    }
+
+    pub fn header_log_overflow(&self) -> bool {
+        self.get_bool(15)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u32 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct AerCapabilitiesControl(u32);
+
+impl AerCapabilitiesControl {
+    pub fn first_error_pointer(&self) -> u8 {
+        (self.0 & 0x1F) as u8
+    }
+
+    pub fn ecrc_generation_capable(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn ecrc_generation_enable(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn ecrc_check_capable(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    pub fn ecrc_check_enable(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    pub fn multiple_header_recording_capable(&self) -> bool {
+        self.get_bool(9)
+    }
+
+    pub fn multiple_header_recording_enable(&self) -> bool {
+        self.get_bool(10)
+    }
+
+    pub fn tlp_prefix_log_present(&self) -> bool {
+        self.get_bool(11)
+    }
+
+    pub fn completion_timeout_prefix_header_log_capable(&self) -> bool {
+        self.get_bool(12)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u32 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RootErrorCommand(u32);
+
+impl RootErrorCommand {
+    pub fn correctable_error_reporting_enable(&self) -> bool {
+        (self.0 & 0x01) == 0x01
+    }
+
+    pub fn non_fatal_error_reporting_enable(&self) -> bool {
+        (self.0 & 0x02) == 0x02
+    }
+
+    pub fn fatal_error_reporting_enable(&self) -> bool {
+        (self.0 & 0x04) == 0x04
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RootErrorStatus(u32);
+
+impl RootErrorStatus {
+    pub fn is_empty(&self) -> bool {
+        (self.0 & 0x0000_007F) == 0
+    }
+
+    pub fn err_cor_received(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn multiple_err_cor_received(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn err_fatal_nonfatal_received(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn multiple_err_fatal_nonfatal_received(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn first_uncorrectable_fatal(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn non_fatal_error_messages_received(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn fatal_error_messages_received(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn advanced_error_interrupt_message_number(&self) -> u8 {
+        (self.0 >> 27) as u8
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u32 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PciConfig;
+    use crate::capability::ExtendedCapability;
+    use crate::memory::Memory;
+    use crate::testing::{config, function_with_ext_cap};
+
+    #[test]
+    fn decode_and_clear() {
+        let mut data = function_with_ext_cap(0x0001, 2, &[]);
+        // Make the function a root port.
+        data[0x42] = 0x42;
+        data[0x104..0x108].copy_from_slice(&0x0010_4000u32.to_le_bytes());
+        data[0x108..0x10C].copy_from_slice(&0x0040_0000u32.to_le_bytes());
+        data[0x10C..0x110].copy_from_slice(&0x0006_2030u32.to_le_bytes());
+        data[0x110..0x114].copy_from_slice(&0x0000_2001u32.to_le_bytes());
+        data[0x114..0x118].copy_from_slice(&0x0000_E000u32.to_le_bytes());
+        data[0x118..0x11C].copy_from_slice(&0x0000_00B4u32.to_le_bytes());
+        data[0x11C..0x120].copy_from_slice(&0x4A00_0001u32.to_le_bytes());
+        data[0x12C..0x130].copy_from_slice(&0x0000_0007u32.to_le_bytes());
+        data[0x130..0x134].copy_from_slice(&0x0800_0005u32.to_le_bytes());
+        data[0x134..0x138].copy_from_slice(&0x0300_0100u32.to_le_bytes());
+
+        let cfg = config(&data);
+        let decode = |cfg: &PciConfig<Memory>| match cfg
+            .extended_capabilities()
+            .next()
+            .map(|c| c.decode())
+        {
+            Some(ExtendedCapability::Aer(aer)) => aer,
+            _ => panic!("expected an AER capability"),
+        };
+
+        let aer = decode(&cfg);
+        let ue = aer.uncorrectable_status();
+        assert!(ue.completion_timeout());
+        assert!(ue.unsupported_request_error());
+        assert!(!ue.malformed_tlp());
+        assert!(aer.uncorrectable_mask().uncorrectable_internal_error());
+        assert!(aer.uncorrectable_severity().data_link_protocol_error());
+        assert!(aer.uncorrectable_severity().malformed_tlp());
+
+        let ce = aer.correctable_status();
+        assert!(ce.receiver_error());
+        assert!(ce.advisory_non_fatal_error());
+        assert!(aer.correctable_mask().header_log_overflow());
+
+        assert_eq!(aer.first_error_pointer(), 0x14);
+        assert!(aer.capabilities_control().ecrc_generation_capable());
+        assert!(aer.capabilities_control().ecrc_check_capable());
+        assert_eq!(aer.header_log()[0], 0x4A00_0001);
+
+        assert!(
+            aer.root_error_command()
+                .unwrap()
+                .fatal_error_reporting_enable()
+        );
+        let root = aer.root_error_status().unwrap();
+        assert!(root.err_cor_received());
+        assert!(root.err_fatal_nonfatal_received());
+        assert_eq!(root.advanced_error_interrupt_message_number(), 1);
+        assert_eq!(aer.correctable_source_id(), Some(0x0100));
+        assert_eq!(aer.uncorrectable_source_id(), Some(0x0300));
+
+        // The memory backend has no RW1C semantics, so this only checks what gets written.
+        aer.clear().unwrap();
+        let aer = decode(&cfg);
+        assert_eq!(aer.uncorrectable_status().bits(), 0x0010_4000);
+        assert_eq!(
+            aer.root_error_status()
+                .unwrap()
+                .advanced_error_interrupt_message_number(),
+            0
+        );
+
+        // An endpoint ends the capability before the root registers.
+        data[0x42] = 0x02;
+        let cfg = config(&data);
+        let aer = decode(&cfg);
+        assert!(aer.root_error_command().is_none());
+        assert!(aer.root_error_status().is_none());
+        assert!(aer.correctable_source_id().is_none());
+        aer.clear().unwrap();
+        assert_eq!(aer.raw().read32(OFFSET_ROOT_ERROR_STATUS), 0x0800_0005);
+        assert!(aer.clear_root_error_status(RootErrorStatus(0x7F)).is_err());
+    }
+}
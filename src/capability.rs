@@ -0,0 +1,698 @@
+use super::acs::AcsCapability;
+use super::aer::AerCapability;
+use super::error;
+use super::msi::{MsiCapability, MsiXCapability};
+use super::pcie::PciECapability;
+use super::pm::PmCapability;
+use super::sriov::SrIovCapability;
+use super::{
+    CapabilityId, ExtendedCapabilityId, HeaderType, Method, OFFSET_HEADER_TYPE, PciCapability,
+    PciExtendedCapability,
+};
+use std::rc::Rc;
+
+#[derive(Clone, Debug)]
+pub enum Capability<T: Method> {
+    Null(NullCapability<T>),
+    Pm(PmCapability<T>),
+    Agp(AgpCapability<T>),
+    Vpd(VpdCapability<T>),
+    SlotId(SlotIdCapability<T>),
+    Msi(MsiCapability<T>),
+    CompatPciHotSwap(HotSwapCapability<T>),
+    PciX(PciXCapability<T>),
+    HyperTransport(HyperTransportCapability<T>),
+    VendorSpecific(VendorSpecificCapability<T>),
+    DebugPort(DebugPortCapability<T>),
+    CompatPciCrc(CentralResourceControlCapability<T>),
+    PciHotPlug(HotPlugCapability<T>),
+    PciBridgeSubVendorId(BridgeSubsystemCapability<T>),
+    Agp8x(AgpCapability<T>),
+    SecureDevice(SecureDeviceCapability<T>),
+    PciE(PciECapability<T>),
+    MsiX(MsiXCapability<T>),
+    SataConfig(SataCapability<T>),
+    AdvanedFeature(AdvancedFeatureCapability<T>),
+    EnhancedAllocation(EnhancedAllocationCapability<T>),
+    FlatteningPortalBridge(FpbCapability<T>),
+    Unknown(RawCapability<T>),
+}
+
+impl<T: Method> Capability<T> {
+    pub fn decode(cap: &PciCapability<T>) -> Self {
+        let raw = RawCapability::from(cap);
+        match cap.id() {
+            Some(CapabilityId::Null) => Capability::Null(NullCapability { raw }),
+            Some(CapabilityId::Pm) => Capability::Pm(PmCapability::new(raw)),
+            Some(CapabilityId::Agp) => Capability::Agp(AgpCapability::new(raw)),
+            Some(CapabilityId::Vpd) => Capability::Vpd(VpdCapability::new(raw)),
+            Some(CapabilityId::SlotId) => Capability::SlotId(SlotIdCapability::new(raw)),
+            Some(CapabilityId::Msi) => Capability::Msi(MsiCapability::new(raw)),
+            Some(CapabilityId::CompatPciHotSwap) => {
+                Capability::CompatPciHotSwap(HotSwapCapability::new(raw))
+            }
+            Some(CapabilityId::PciX) => Capability::PciX(PciXCapability::new(raw)),
+            Some(CapabilityId::HyperTransport) => {
+                Capability::HyperTransport(HyperTransportCapability::new(raw))
+            }
+            Some(CapabilityId::VendorSpecific) => {
+                Capability::VendorSpecific(VendorSpecificCapability::new(raw))
+            }
+            Some(CapabilityId::DebugPort) => Capability::DebugPort(DebugPortCapability::new(raw)),
+            Some(CapabilityId::CompatPciCrc) => {
+                Capability::CompatPciCrc(CentralResourceControlCapability { raw })
+            }
+            Some(CapabilityId::PciHotPlug) => Capability::PciHotPlug(HotPlugCapability { raw }),
+            Some(CapabilityId::PciBridgeSubVendorId) => {
+                Capability::PciBridgeSubVendorId(BridgeSubsystemCapability::new(raw))
+            }
+            Some(CapabilityId::Agp8x) => Capability::Agp8x(AgpCapability::new(raw)),
+            Some(CapabilityId::SecureDevice) => {
+                Capability::SecureDevice(SecureDeviceCapability { raw })
+            }
+            Some(CapabilityId::PciE) => Capability::PciE(PciECapability::new(raw)),
+            Some(CapabilityId::MsiX) => Capability::MsiX(MsiXCapability::new(raw)),
+            Some(CapabilityId::SataConfig) => Capability::SataConfig(SataCapability::new(raw)),
+            Some(CapabilityId::AdvanedFeature) => {
+                Capability::AdvanedFeature(AdvancedFeatureCapability::new(raw))
+            }
+            Some(CapabilityId::EnhancedAllocation) => {
+                Capability::EnhancedAllocation(EnhancedAllocationCapability::new(raw))
+            }
+            Some(CapabilityId::FlatteningPortalBridge) => {
+                Capability::FlatteningPortalBridge(FpbCapability::new(raw))
+            }
+            None => Capability::Unknown(raw),
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        match self {
+            Capability::Null(c) => &c.raw,
+            Capability::Pm(c) => &c.raw,
+            Capability::Agp(c) | Capability::Agp8x(c) => &c.raw,
+            Capability::Vpd(c) => &c.raw,
+            Capability::SlotId(c) => &c.raw,
+            Capability::Msi(c) => &c.raw,
+            Capability::CompatPciHotSwap(c) => &c.raw,
+            Capability::PciX(c) => &c.raw,
+            Capability::HyperTransport(c) => &c.raw,
+            Capability::VendorSpecific(c) => &c.raw,
+            Capability::DebugPort(c) => &c.raw,
+            Capability::CompatPciCrc(c) => &c.raw,
+            Capability::PciHotPlug(c) => &c.raw,
+            Capability::PciBridgeSubVendorId(c) => &c.raw,
+            Capability::SecureDevice(c) => &c.raw,
+            Capability::PciE(c) => &c.raw,
+            Capability::MsiX(c) => &c.raw,
+            Capability::SataConfig(c) => &c.raw,
+            Capability::AdvanedFeature(c) => &c.raw,
+            Capability::EnhancedAllocation(c) => &c.raw,
+            Capability::FlatteningPortalBridge(c) => &c.raw,
+            Capability::Unknown(raw) => raw,
+        }
+    }
+
+    pub fn offset(&self) -> u8 {
+        self.raw().offset()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ExtendedCapability<T: Method> {
+    Aer(AerCapability<T>),
+    Acs(AcsCapability<T>),
+    SrIov(SrIovCapability<T>),
+    Other(PciExtendedCapability<T>),
+}
+
+impl<T: Method> ExtendedCapability<T> {
+    pub fn decode(cap: &PciExtendedCapability<T>) -> Self {
+        match cap.id() {
+            Some(ExtendedCapabilityId::Aer) => {
+                ExtendedCapability::Aer(AerCapability::new(cap.clone()))
+            }
+            Some(ExtendedCapabilityId::Acs) => {
+                ExtendedCapability::Acs(AcsCapability::new(cap.clone()))
+            }
+            Some(ExtendedCapabilityId::SrIov) => {
+                ExtendedCapability::SrIov(SrIovCapability::new(cap.clone()))
+            }
+            _ => ExtendedCapability::Other(cap.clone()),
+        }
+    }
+
+    pub fn raw(&self) -> &PciExtendedCapability<T> {
+        match self {
+            ExtendedCapability::Aer(c) => &c.raw,
+            ExtendedCapability::Acs(c) => &c.raw,
+            ExtendedCapability::SrIov(c) => &c.raw,
+            ExtendedCapability::Other(raw) => raw,
+        }
+    }
+
+    pub fn offset(&self) -> u16 {
+        self.raw().offset()
+    }
+}
+
+// Register access relative to the start of a capability.
+#[derive(Clone, Debug)]
+pub struct RawCapability<T: Method> {
+    method: Rc<T>,
+    offset: u8,
+    id: u8,
+    next_pointer: u8,
+}
+
+impl<T: Method> RawCapability<T> {
+    pub fn from(cap: &PciCapability<T>) -> Self {
+        RawCapability {
+            method: cap.method.clone(),
+            offset: cap.offset,
+            id: cap.id,
+            next_pointer: cap.next_pointer,
+        }
+    }
+
+    pub fn offset(&self) -> u8 {
+        self.offset
+    }
+
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    pub fn next_pointer(&self) -> u8 {
+        self.next_pointer
+    }
+
+    pub fn read8(&self, register: u16) -> u8 {
+        self.method.read8(self.offset as u16 + register)
+    }
+
+    pub fn read16(&self, register: u16) -> u16 {
+        self.method.read16(self.offset as u16 + register)
+    }
+
+    pub fn read32(&self, register: u16) -> u32 {
+        self.method.read32(self.offset as u16 + register)
+    }
+
+    pub fn write8(&self, register: u16, value: u8) -> Result<(), error::Error> {
+        self.method.write8(self.offset as u16 + register, value)
+    }
+
+    pub fn write16(&self, register: u16, value: u16) -> Result<(), error::Error> {
+        self.method.write16(self.offset as u16 + register, value)
+    }
+
+    pub fn write32(&self, register: u16, value: u32) -> Result<(), error::Error> {
+        self.method.write32(self.offset as u16 + register, value)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NullCapability<T: Method> {
+    raw: RawCapability<T>,
+}
+
+impl<T: Method> NullCapability<T> {
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AgpCapability<T: Method> {
+    raw: RawCapability<T>,
+    revision: u8,
+    status: u32,
+    command: u32,
+}
+
+impl<T: Method> AgpCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        AgpCapability {
+            revision: raw.read8(0x02),
+            status: raw.read32(0x04),
+            command: raw.read32(0x08),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn major_revision(&self) -> u8 {
+        self.revision >> 4
+    }
+
+    pub fn minor_revision(&self) -> u8 {
+        self.revision & 0x0F
+    }
+
+    pub fn status(&self) -> u32 {
+        self.status
+    }
+
+    pub fn command(&self) -> u32 {
+        self.command
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VpdCapability<T: Method> {
+    raw: RawCapability<T>,
+    address: u16,
+    data: u32,
+}
+
+impl<T: Method> VpdCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        VpdCapability {
+            address: raw.read16(0x02),
+            data: raw.read32(0x04),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn address(&self) -> u16 {
+        self.address & 0x7FFF
+    }
+
+    pub fn flag(&self) -> bool {
+        (self.address & 0x8000) == 0x8000
+    }
+
+    pub fn data(&self) -> u32 {
+        self.data
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SlotIdCapability<T: Method> {
+    raw: RawCapability<T>,
+    expansion_slot: u8,
+    chassis_number: u8,
+}
+
+impl<T: Method> SlotIdCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        SlotIdCapability {
+            expansion_slot: raw.read8(0x02),
+            chassis_number: raw.read8(0x03),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn slots(&self) -> u8 {
+        self.expansion_slot & 0x1F
+    }
+
+    pub fn first_in_chassis(&self) -> bool {
+        (self.expansion_slot & 0x20) == 0x20
+    }
+
+    pub fn chassis_number(&self) -> u8 {
+        self.chassis_number
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HotSwapCapability<T: Method> {
+    raw: RawCapability<T>,
+    control_status: u8,
+}
+
+impl<T: Method> HotSwapCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        HotSwapCapability {
+            control_status: raw.read8(0x02),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn control_status(&self) -> u8 {
+        self.control_status
+    }
+}
+
+// Bridges keep a secondary status register at 0x02 and a bridge status register at 0x04
+// where other functions have their command and status registers.
+#[derive(Clone, Debug)]
+pub struct PciXCapability<T: Method> {
+    raw: RawCapability<T>,
+    command: Option<u16>,
+    status: Option<u32>,
+    secondary_status: Option<u16>,
+    bridge_status: Option<u32>,
+}
+
+impl<T: Method> PciXCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        let bridge = HeaderType(raw.method.read8(OFFSET_HEADER_TYPE)).type1();
+        let (register2, register4) = (raw.read16(0x02), raw.read32(0x04));
+
+        PciXCapability {
+            command: (!bridge).then_some(register2),
+            status: (!bridge).then_some(register4),
+            secondary_status: bridge.then_some(register2),
+            bridge_status: bridge.then_some(register4),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn command(&self) -> Option<u16> {
+        self.command
+    }
+
+    pub fn status(&self) -> Option<u32> {
+        self.status
+    }
+
+    pub fn secondary_status(&self) -> Option<u16> {
+        self.secondary_status
+    }
+
+    pub fn bridge_status(&self) -> Option<u32> {
+        self.bridge_status
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HyperTransportCapability<T: Method> {
+    raw: RawCapability<T>,
+    command: u16,
+}
+
+impl<T: Method> HyperTransportCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        HyperTransportCapability {
+            command: raw.read16(0x02),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn command(&self) -> u16 {
+        self.command
+    }
+
+    // Slave/primary and host/secondary interfaces only use the top three bits.
+    pub fn capability_type(&self) -> u8 {
+        let ty = (self.command >> 11) as u8;
+        if ty < 0b01000 { ty & 0b11100 } else { ty }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VendorSpecificCapability<T: Method> {
+    raw: RawCapability<T>,
+    length: u8,
+}
+
+impl<T: Method> VendorSpecificCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        VendorSpecificCapability {
+            length: raw.read8(0x02),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn length(&self) -> u8 {
+        self.length
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DebugPortCapability<T: Method> {
+    raw: RawCapability<T>,
+    port: u16,
+}
+
+impl<T: Method> DebugPortCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        DebugPortCapability {
+            port: raw.read16(0x02),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn port_offset(&self) -> u16 {
+        self.port & 0x1FFF
+    }
+
+    pub fn bar_number(&self) -> u8 {
+        (self.port >> 13) as u8
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CentralResourceControlCapability<T: Method> {
+    raw: RawCapability<T>,
+}
+
+impl<T: Method> CentralResourceControlCapability<T> {
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HotPlugCapability<T: Method> {
+    raw: RawCapability<T>,
+}
+
+impl<T: Method> HotPlugCapability<T> {
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BridgeSubsystemCapability<T: Method> {
+    raw: RawCapability<T>,
+    subsystem_vendor_id: u16,
+    subsystem_id: u16,
+}
+
+impl<T: Method> BridgeSubsystemCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        BridgeSubsystemCapability {
+            subsystem_vendor_id: raw.read16(0x04),
+            subsystem_id: raw.read16(0x06),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn subsystem_vendor_id(&self) -> u16 {
+        self.subsystem_vendor_id
+    }
+
+    pub fn subsystem_id(&self) -> u16 {
+        self.subsystem_id
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SecureDeviceCapability<T: Method> {
+    raw: RawCapability<T>,
+}
+
+impl<T: Method> SecureDeviceCapability<T> {
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SataCapability<T: Method> {
+    raw: RawCapability<T>,
+    revision: u8,
+    bar: u32,
+}
+
+impl<T: Method> SataCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        SataCapability {
+            revision: raw.read8(0x02),
+            bar: raw.read32(0x04),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn major_revision(&self) -> u8 {
+        self.revision >> 4
+    }
+
+    pub fn minor_revision(&self) -> u8 {
+        self.revision & 0x0F
+    }
+
+    pub fn bar_location(&self) -> u8 {
+        (self.bar & 0x0F) as u8
+    }
+
+    pub fn bar_offset(&self) -> u32 {
+        (self.bar >> 4) & 0x000F_FFFF
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AdvancedFeatureCapability<T: Method> {
+    raw: RawCapability<T>,
+    length: u8,
+    capabilities: u8,
+    control: u8,
+    status: u8,
+}
+
+impl<T: Method> AdvancedFeatureCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        AdvancedFeatureCapability {
+            length: raw.read8(0x02),
+            capabilities: raw.read8(0x03),
+            control: raw.read8(0x04),
+            status: raw.read8(0x05),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn length(&self) -> u8 {
+        self.length
+    }
+
+    pub fn transactions_pending_capable(&self) -> bool {
+        (self.capabilities & 0x01) == 0x01
+    }
+
+    pub fn function_level_reset_capable(&self) -> bool {
+        (self.capabilities & 0x02) == 0x02
+    }
+
+    pub fn control(&self) -> u8 {
+        self.control
+    }
+
+    pub fn transactions_pending(&self) -> bool {
+        (self.status & 0x01) == 0x01
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EnhancedAllocationCapability<T: Method> {
+    raw: RawCapability<T>,
+    entries: u8,
+}
+
+impl<T: Method> EnhancedAllocationCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        EnhancedAllocationCapability {
+            entries: raw.read8(0x02) & 0x3F,
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn entries(&self) -> u8 {
+        self.entries
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FpbCapability<T: Method> {
+    raw: RawCapability<T>,
+    capabilities: u32,
+}
+
+impl<T: Method> FpbCapability<T> {
+    fn new(raw: RawCapability<T>) -> Self {
+        FpbCapability {
+            capabilities: raw.read32(0x04),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn capabilities(&self) -> u32 {
+        self.capabilities
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{config, function_with_cap};
+
+    #[test]
+    fn pci_x_bridge() {
+        let mut data = function_with_cap(0x07, &[0x03, 0x00, 0x00, 0x01, 0x01, 0x00]);
+
+        let decode = |data: &[u8]| {
+            let cfg = config(data);
+            let Some(Capability::PciX(pcix)) = cfg.capabilities().next().map(|c| c.decode()) else {
+                panic!("expected a PCI-X capability");
+            };
+            pcix
+        };
+
+        let pcix = decode(&data);
+        assert_eq!(pcix.command(), Some(0x0003));
+        assert_eq!(pcix.status(), Some(0x0001_0100));
+        assert!(pcix.secondary_status().is_none());
+
+        data[0x0E] = 0x01;
+        let pcix = decode(&data);
+        assert!(pcix.command().is_none());
+        assert!(pcix.status().is_none());
+        assert_eq!(pcix.secondary_status(), Some(0x0003));
+        assert_eq!(pcix.bridge_status(), Some(0x0001_0100));
+    }
+}
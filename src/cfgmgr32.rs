@@ -1,13 +1,20 @@
 use super::{
-    Method, NOT_USED, OFFSET_BASE_CLASS, OFFSET_BIST, OFFSET_CACHE_LINE_SIZE,
+    CONFIG_SPACE_SIZE, Method, NOT_USED, OFFSET_BASE_CLASS, OFFSET_BIST, OFFSET_CACHE_LINE_SIZE,
     OFFSET_CAPABILITIES_POINTER, OFFSET_COMMAND, OFFSET_DEVICE_ID, OFFSET_HEADER_TYPE,
     OFFSET_INTERRUPT_LINE, OFFSET_INTERRUPT_PIN, OFFSET_MASTER_LATENCY_TIMER, OFFSET_PROG_INTF,
     OFFSET_REVISION_ID, OFFSET_STATUS, OFFSET_SUB_CLASS, OFFSET_TYPE0_BAR0, OFFSET_TYPE0_BAR1,
     OFFSET_TYPE0_BAR2, OFFSET_TYPE0_BAR3, OFFSET_TYPE0_BAR4, OFFSET_TYPE0_BAR5,
     OFFSET_TYPE0_CARDBUS, OFFSET_TYPE0_EXPANSION, OFFSET_TYPE0_SUBSYSTEM_ID,
-    OFFSET_TYPE0_SUBSYSTEM_VENDOR_ID, OFFSET_TYPE1_EXPANSION, OFFSET_TYPE1_PRIMARY_BUS_NUM,
+    OFFSET_TYPE0_SUBSYSTEM_VENDOR_ID, OFFSET_TYPE1_BRIDGE_CONTROL, OFFSET_TYPE1_EXPANSION,
+    OFFSET_TYPE1_IO_BASE, OFFSET_TYPE1_IO_BASE_UPPER, OFFSET_TYPE1_IO_LIMIT,
+    OFFSET_TYPE1_IO_LIMIT_UPPER, OFFSET_TYPE1_MEMORY_BASE, OFFSET_TYPE1_MEMORY_LIMIT,
+    OFFSET_TYPE1_PREFETCHABLE_LIMIT_UPPER, OFFSET_TYPE1_PREFETCHABLE_MEMORY_BASE,
+    OFFSET_TYPE1_PREFETCHABLE_MEMORY_LIMIT, OFFSET_TYPE1_PRIMARY_BUS_NUM,
     OFFSET_TYPE1_SECONDARY_BUS_NUM, OFFSET_TYPE1_SECONDARY_LATENCY_TIMER,
-    OFFSET_TYPE1_SUBORDINATE_BUS_NUM, OFFSET_VENDOR_ID, error,
+    OFFSET_TYPE1_SECONDARY_STATUS, OFFSET_TYPE1_SUBORDINATE_BUS_NUM,
+    OFFSET_TYPE2_CAPABILITIES_POINTER, OFFSET_TYPE2_IO_BASE1, OFFSET_TYPE2_LEGACY_BASE,
+    OFFSET_TYPE2_SECONDARY_STATUS, OFFSET_TYPE2_SUBSYSTEM_ID, OFFSET_TYPE2_SUBSYSTEM_VENDOR_ID,
+    OFFSET_VENDOR_ID, PciAddress, error,
 };
 use bytes::{Buf, Bytes};
 use std::sync::OnceLock;
@@ -36,16 +43,29 @@ pub struct Cfgmgr32 {
 }
 
 impl Method for Cfgmgr32 {
-    fn try_from(bus: u8, device: u8, func: u8) -> Result<Self, error::Error> {
+    fn try_from(address: PciAddress) -> Result<Self, error::Error> {
+        // Device nodes do not expose a segment number.
+        if address.domain() != 0 {
+            return Err(error::Error::UnsupportedSegment(address.domain()));
+        }
+
         let node = get_dev_nodes()
             .iter()
-            .find(|n| n.bus == bus && n.device == device && n.func == func)
+            .find(|n| {
+                n.bus == address.bus()
+                    && n.device == address.device()
+                    && n.func == address.function()
+            })
             .cloned()
             .unwrap_or_default();
         Ok(Cfgmgr32 { node })
     }
 
-    fn read8(&self, offset: u8) -> u8 {
+    fn config_size(&self) -> u16 {
+        CONFIG_SPACE_SIZE
+    }
+
+    fn read8(&self, offset: u16) -> u8 {
         match offset {
             OFFSET_REVISION_ID => self.get_revision(),
             OFFSET_PROG_INTF => self.get_prog_intf(),
@@ -62,11 +82,14 @@ impl Method for Cfgmgr32 {
             OFFSET_TYPE1_SECONDARY_BUS_NUM => self.get_secondary_bus_number(),
             OFFSET_TYPE1_SUBORDINATE_BUS_NUM => 0,
             OFFSET_TYPE1_SECONDARY_LATENCY_TIMER => 0,
+            OFFSET_TYPE1_IO_BASE => 0,
+            OFFSET_TYPE1_IO_LIMIT => 0,
+            OFFSET_TYPE2_CAPABILITIES_POINTER => 0,
             _ => unimplemented!(),
         }
     }
 
-    fn read16(&self, offset: u8) -> u16 {
+    fn read16(&self, offset: u16) -> u16 {
         match offset {
             OFFSET_VENDOR_ID => self.get_vendor(),
             OFFSET_DEVICE_ID => self.get_device(),
@@ -74,11 +97,22 @@ impl Method for Cfgmgr32 {
             OFFSET_STATUS => 0,
             OFFSET_TYPE0_SUBSYSTEM_VENDOR_ID => self.get_subsys_id(),
             OFFSET_TYPE0_SUBSYSTEM_ID => self.get_subsys_vendor(),
+            OFFSET_TYPE1_SECONDARY_STATUS => 0,
+            OFFSET_TYPE1_MEMORY_BASE => 0,
+            OFFSET_TYPE1_MEMORY_LIMIT => 0,
+            OFFSET_TYPE1_PREFETCHABLE_MEMORY_BASE => 0,
+            OFFSET_TYPE1_PREFETCHABLE_MEMORY_LIMIT => 0,
+            OFFSET_TYPE1_IO_BASE_UPPER => 0,
+            OFFSET_TYPE1_IO_LIMIT_UPPER => 0,
+            OFFSET_TYPE1_BRIDGE_CONTROL => 0,
+            OFFSET_TYPE2_SECONDARY_STATUS => 0,
+            OFFSET_TYPE2_SUBSYSTEM_VENDOR_ID => self.get_subsys_vendor(),
+            OFFSET_TYPE2_SUBSYSTEM_ID => self.get_subsys_id(),
             _ => unimplemented!(),
         }
     }
 
-    fn read32(&self, offset: u8) -> u32 {
+    fn read32(&self, offset: u16) -> u32 {
         match offset {
             OFFSET_TYPE0_BAR0 => 0,
             OFFSET_TYPE0_BAR1 => 0,
@@ -90,10 +124,27 @@ impl Method for Cfgmgr32 {
             OFFSET_TYPE0_EXPANSION => 0,
             //OFFSET_TYPE1_BAR0 => 0,
             //OFFSET_TYPE1_BAR1 => 0,
+            //OFFSET_TYPE1_PREFETCHABLE_BASE_UPPER => 0,
+            OFFSET_TYPE1_PREFETCHABLE_LIMIT_UPPER => 0,
             OFFSET_TYPE1_EXPANSION => 0,
+            // The other CardBus registers share their offsets with the arms above.
+            OFFSET_TYPE2_IO_BASE1 => 0,
+            OFFSET_TYPE2_LEGACY_BASE => 0,
             _ => unimplemented!(),
         }
     }
+
+    fn write8(&self, _offset: u16, _value: u8) -> Result<(), error::Error> {
+        Err(error::Error::ReadOnly)
+    }
+
+    fn write16(&self, _offset: u16, _value: u16) -> Result<(), error::Error> {
+        Err(error::Error::ReadOnly)
+    }
+
+    fn write32(&self, _offset: u16, _value: u32) -> Result<(), error::Error> {
+        Err(error::Error::ReadOnly)
+    }
 }
 
 impl Cfgmgr32 {
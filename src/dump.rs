@@ -0,0 +1,131 @@
+use super::PciAddress;
+use super::error::Error;
+use super::memory::MemorySpace;
+use nom::{
+    IResult, Parser,
+    branch::alt,
+    bytes::complete::{tag, take_while_m_n},
+    character::complete::{hex_digit1, space0, space1},
+    combinator::{eof, map_res, opt},
+    multi::many1,
+    sequence::{preceded, terminated},
+};
+use std::fs;
+use std::path::Path;
+
+// Reads the output of `lspci -x`, `lspci -xxx` or `lspci -xxxx`.
+pub fn parse(input: &str) -> Result<MemorySpace, Error> {
+    let mut space = MemorySpace::new();
+    let mut current = None;
+    let mut data = vec![];
+
+    for line in input.lines() {
+        if let Ok((_, (offset, bytes))) = row(line) {
+            if current.is_none() {
+                return Err(Error::Parse(format!("no device for `{line}`")));
+            }
+
+            let s = offset as usize;
+            let e = s + bytes.len();
+            if data.len() < e {
+                data.resize(e, 0);
+            }
+            data[s..e].copy_from_slice(&bytes);
+        } else if let Ok((_, address)) = header(line) {
+            if let Some(address) = current.replace(address) {
+                space.insert(address, &data)?;
+            }
+            data.clear();
+        }
+    }
+
+    if let Some(address) = current {
+        space.insert(address, &data)?;
+    }
+
+    Ok(space)
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<MemorySpace, Error> {
+    let content = fs::read_to_string(path)?;
+    parse(&content)
+}
+
+fn hex<'a, O, F>(
+    min: usize,
+    max: usize,
+    f: F,
+) -> impl Parser<&'a str, Output = O, Error = nom::error::Error<&'a str>>
+where
+    F: Fn(&str) -> Result<O, std::num::ParseIntError>,
+{
+    map_res(take_while_m_n(min, max, |c: char| c.is_ascii_hexdigit()), f)
+}
+
+fn header(input: &str) -> IResult<&str, PciAddress> {
+    map_res(
+        (
+            opt(terminated(
+                hex(4, 4, |v| u16::from_str_radix(v, 16)),
+                tag(":"),
+            )),
+            terminated(hex(2, 2, |v| u8::from_str_radix(v, 16)), tag(":")),
+            terminated(hex(2, 2, |v| u8::from_str_radix(v, 16)), tag(".")),
+            terminated(hex(1, 1, |v| u8::from_str_radix(v, 16)), alt((space1, eof))),
+        ),
+        |(s, b, d, f)| PciAddress::new(s.unwrap_or(0), b, d, f),
+    )
+    .parse(input)
+}
+
+fn row(input: &str) -> IResult<&str, (u16, Vec<u8>)> {
+    terminated(
+        (
+            terminated(
+                map_res(hex_digit1, |v| u16::from_str_radix(v, 16)),
+                tag(":"),
+            ),
+            many1(preceded(space1, hex(2, 2, |v| u8::from_str_radix(v, 16)))),
+        ),
+        (space0, eof),
+    )
+    .parse(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Method;
+
+    const DUMP: &str = "\
+00:00.0 Host bridge: Intel Corporation 440FX - 82441FX PMC [Natoma] (rev 02)
+00: 86 80 37 12 07 00 00 00 02 00 00 06 00 00 00 00
+10: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+20: 00 00 00 00 00 00 00 00 00 00 00 00 f4 1a 00 11
+30: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00
+
+0001:02:1f.3 Ethernet controller: Red Hat, Inc. Virtio network device
+\tSubsystem: Red Hat, Inc. Device 0001
+00: f4 1a 00 10 07 05 10 00 00 00 00 02 00 00 00 00
+100: 01 00 01 00 00 00 00 00 00 00 00 00 00 00 00 00
+";
+
+    #[test]
+    fn parse_dump() {
+        let space = parse(DUMP).unwrap();
+        assert_eq!(space.len(), 2);
+
+        let host = space.get("00:00.0".parse().unwrap());
+        assert_eq!(host.read16(0x00), 0x8086);
+        assert_eq!(host.read16(0x02), 0x1237);
+        assert_eq!(host.read8(0x0B), 0x06);
+        assert_eq!(host.config_size(), 0x100);
+
+        let nic = space.get("0001:02:1f.3".parse().unwrap());
+        assert_eq!(nic.read16(0x00), 0x1AF4);
+        assert_eq!(nic.read32(0x100), 0x0001_0001);
+        assert_eq!(nic.config_size(), 0x1000);
+
+        assert!(!space.get("00:01.0".parse().unwrap()).present());
+    }
+}
@@ -1,18 +1,17 @@
-use super::Method;
 use super::error;
+use super::{EXTENDED_CONFIG_SPACE_SIZE, Method, PciAddress};
 use acpi::MemoryMappedConfiguration;
-use bytes::{Buf, BufMut, Bytes, BytesMut};
 use libc;
 use std::ffi::CString;
 use std::io;
 use std::os::raw::c_char;
 use std::path::Path;
 use std::ptr;
-use std::slice;
+use std::rc::Rc;
 use std::sync::OnceLock;
 
 const MEM_DEV: &str = "/dev/mem";
-const SIZE: usize = 64;
+const SIZE: usize = 4096;
 
 static MCFG: OnceLock<Option<MemoryMappedConfiguration>> = OnceLock::new();
 
@@ -24,31 +23,56 @@ pub fn support() -> bool {
 
 #[derive(Clone, Debug)]
 pub struct Ecam {
-    data: Bytes,
+    mem: Rc<Memory>,
 }
 
 impl Method for Ecam {
-    fn try_from(bus: u8, device: u8, func: u8) -> Result<Self, error::Error> {
-        let data = read_mem(bus, device, func)?;
-        Ok(Ecam { data })
+    fn try_from(address: PciAddress) -> Result<Self, error::Error> {
+        let mem = map_mem(address)?;
+        Ok(Ecam { mem: Rc::new(mem) })
     }
 
-    fn read8(&self, offset: u8) -> u8 {
-        let s = offset as usize;
-        let e = s + 1;
-        self.data.slice(s..e).get_u8()
+    fn config_size(&self) -> u16 {
+        EXTENDED_CONFIG_SPACE_SIZE
     }
 
-    fn read16(&self, offset: u8) -> u16 {
-        let s = offset as usize;
-        let e = s + 2;
-        self.data.slice(s..e).get_u16_le()
+    fn read8(&self, offset: u16) -> u8 {
+        self.mem.read::<u8>(offset as usize).unwrap_or(0xFF)
     }
 
-    fn read32(&self, offset: u8) -> u32 {
-        let s = offset as usize;
-        let e = s + 4;
-        self.data.slice(s..e).get_u32_le()
+    fn read16(&self, offset: u16) -> u16 {
+        self.mem
+            .read::<u16>(offset as usize)
+            .unwrap_or_else(|| u16::from_le_bytes(self.read_bytes(offset)))
+    }
+
+    fn read32(&self, offset: u16) -> u32 {
+        self.mem
+            .read::<u32>(offset as usize)
+            .unwrap_or_else(|| u32::from_le_bytes(self.read_bytes(offset)))
+    }
+
+    fn write8(&self, offset: u16, value: u8) -> Result<(), error::Error> {
+        self.mem.write(offset, value)
+    }
+
+    fn write16(&self, offset: u16, value: u16) -> Result<(), error::Error> {
+        self.mem.write(offset, value)
+    }
+
+    fn write32(&self, offset: u16, value: u32) -> Result<(), error::Error> {
+        self.mem.write(offset, value)
+    }
+}
+
+impl Ecam {
+    // Misaligned reads are split into byte reads, which are always naturally aligned.
+    fn read_bytes<const N: usize>(&self, offset: u16) -> [u8; N] {
+        let mut buf = [0xFFu8; N];
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = self.read8(offset + i as u16);
+        }
+        buf
     }
 }
 
@@ -58,30 +82,67 @@ fn init_mcfg() -> Option<MemoryMappedConfiguration> {
     acpi::get::<MemoryMappedConfiguration>("MCFG").ok()
 }
 
-fn read_mem(bus: u8, device: u8, func: u8) -> Result<Bytes, error::Error> {
-    let offset = mem_offset(bus, device, func)?;
-    let file = File::open_read(MEM_DEV)?;
-    let mem = Memory::map(&file, offset)?;
-    Ok(Bytes::from(mem))
+fn map_mem(address: PciAddress) -> Result<Memory, error::Error> {
+    let offset = mem_offset(address)?;
+
+    // Fall back to a read-only mapping if /dev/mem is not writable.
+    let mem = match File::open(MEM_DEV, true) {
+        Ok(file) => Memory::map(&file, offset, true)?,
+        Err(_) => {
+            let file = File::open(MEM_DEV, false)?;
+            Memory::map(&file, offset, false)?
+        }
+    };
+
+    Ok(mem)
 }
 
-fn mem_offset(bus: u8, device: u8, func: u8) -> Result<libc::off_t, error::Error> {
-    let space = MCFG
+fn mem_offset(address: PciAddress) -> Result<libc::off_t, error::Error> {
+    let mcfg = MCFG
         .get_or_init(init_mcfg)
         .as_ref()
-        .and_then(|m| m.spaces.first());
-    if space.is_none() {
-        Err(error::Error::NotFoundAcpiMcfg)
-    } else {
-        let space = space.unwrap();
+        .ok_or(error::Error::NotFoundAcpiMcfg)?;
 
-        let base_offset = i64::from_le_bytes(space.base_address);
-        let offset = (((bus - space.bus_number_start) as i64) << 20)
-            + ((device as i64) << 15)
-            + ((func as i64) << 12)
-            + base_offset;
-        Ok(offset)
+    let allocations: Vec<Allocation> = mcfg
+        .spaces
+        .iter()
+        .map(|s| Allocation {
+            segment: u16::from_le_bytes(s.pci_segment_group_number),
+            start_bus: s.bus_number_start,
+            end_bus: s.bus_number_end,
+            base: i64::from_le_bytes(s.base_address),
+        })
+        .collect();
+    config_offset(&allocations, address)
+}
+
+// One entry of the MCFG table.
+struct Allocation {
+    segment: u16,
+    start_bus: u8,
+    end_bus: u8,
+    base: i64,
+}
+
+fn config_offset(allocations: &[Allocation], address: PciAddress) -> Result<i64, error::Error> {
+    if !allocations.iter().any(|a| a.segment == address.domain()) {
+        return Err(error::Error::UnsupportedSegment(address.domain()));
     }
+
+    let allocation = allocations
+        .iter()
+        .find(|a| {
+            a.segment == address.domain()
+                && a.start_bus <= address.bus()
+                && address.bus() <= a.end_bus
+        })
+        .ok_or(error::Error::NotFoundAcpiMcfgSpace)?;
+
+    let offset = (((address.bus() - allocation.start_bus) as i64) << 20)
+        + ((address.device() as i64) << 15)
+        + ((address.function() as i64) << 12)
+        + allocation.base;
+    Ok(offset)
 }
 
 // -----------------------------------------------------------------------------------------------
@@ -99,14 +160,14 @@ impl Drop for File {
 }
 
 impl File {
-    fn open_read(path: &str) -> Result<File, io::Error> {
+    fn open(path: &str, writable: bool) -> Result<File, io::Error> {
         let path = CString::new(path).unwrap();
-        let fd = unsafe {
-            libc::open(
-                path.as_ptr() as *const c_char,
-                libc::O_RDONLY | libc::O_DSYNC,
-            )
+        let mode = if writable {
+            libc::O_RDWR
+        } else {
+            libc::O_RDONLY
         };
+        let fd = unsafe { libc::open(path.as_ptr() as *const c_char, mode | libc::O_DSYNC) };
 
         if fd < 0 {
             Err(io::Error::last_os_error())
@@ -118,8 +179,10 @@ impl File {
 
 // -----------------------------------------------------------------------------------------------
 
+#[derive(Debug)]
 struct Memory {
     mem: *mut libc::c_void,
+    writable: bool,
 }
 
 impl Drop for Memory {
@@ -130,37 +193,101 @@ impl Drop for Memory {
     }
 }
 
-impl From<Memory> for Bytes {
-    fn from(value: Memory) -> Self {
-        let mut bytes = BytesMut::with_capacity(SIZE);
-
-        unsafe {
-            for p in slice::from_raw_parts(value.mem as *const u8, SIZE) {
-                bytes.put_u8(*p);
-            }
-        }
-
-        bytes.freeze()
-    }
-}
-
 impl Memory {
-    fn map(file: &File, offset: libc::off_t) -> Result<Self, io::Error> {
+    fn map(file: &File, offset: libc::off_t, writable: bool) -> Result<Self, io::Error> {
+        let prot = if writable {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_READ
+        };
         let mem = unsafe {
             libc::mmap(
                 ptr::null_mut(),
                 SIZE,
-                libc::PROT_READ,
-                libc::MAP_PRIVATE,
+                prot,
+                libc::MAP_SHARED,
                 file.fd,
                 offset,
             )
         };
 
-        if mem.is_null() {
+        if mem == libc::MAP_FAILED {
             Err(io::Error::last_os_error())
         } else {
-            Ok(Memory { mem })
+            Ok(Memory { mem, writable })
+        }
+    }
+
+    // ECAM accesses must be naturally aligned.
+    fn read<T: Copy>(&self, offset: usize) -> Option<T> {
+        let size = size_of::<T>();
+        if offset + size > SIZE || offset % size != 0 {
+            return None;
+        }
+
+        let value = unsafe { ptr::read_volatile(self.mem.cast::<u8>().add(offset).cast::<T>()) };
+        Some(value)
+    }
+
+    fn write<T: Copy>(&self, offset: u16, value: T) -> Result<(), error::Error> {
+        let size = size_of::<T>();
+        let s = offset as usize;
+        if s + size > SIZE {
+            return Err(error::Error::OutOfRange(offset));
+        }
+
+        if s % size != 0 {
+            return Err(error::Error::Misaligned(offset));
+        }
+
+        if !self.writable {
+            return Err(error::Error::ReadOnly);
         }
+
+        unsafe { ptr::write_volatile(self.mem.cast::<u8>().add(s).cast::<T>(), value) };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_allocation() {
+        let allocations = [
+            Allocation {
+                segment: 0,
+                start_bus: 0x00,
+                end_bus: 0x7F,
+                base: 0xE000_0000,
+            },
+            Allocation {
+                segment: 0,
+                start_bus: 0x80,
+                end_bus: 0xFF,
+                base: 0xD000_0000,
+            },
+            Allocation {
+                segment: 1,
+                start_bus: 0x10,
+                end_bus: 0x1F,
+                base: 0x3_0000_0000,
+            },
+        ];
+        let offset = |address: &str| config_offset(&allocations, address.parse().unwrap());
+
+        assert_eq!(offset("0000:00:00.0").unwrap(), 0xE000_0000);
+        assert_eq!(offset("0000:03:1f.7").unwrap(), 0xE03F_F000);
+        assert_eq!(offset("0000:81:00.1").unwrap(), 0xD010_1000);
+        assert_eq!(offset("0001:12:00.0").unwrap(), 0x3_0020_0000);
+        assert!(matches!(
+            offset("0001:20:00.0"),
+            Err(error::Error::NotFoundAcpiMcfgSpace)
+        ));
+        assert!(matches!(
+            offset("0002:00:00.0"),
+            Err(error::Error::UnsupportedSegment(2))
+        ));
     }
 }
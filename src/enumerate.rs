@@ -0,0 +1,362 @@
+use super::address::PciAddress;
+use super::capability::ExtendedCapability;
+use super::error;
+use super::{ExtendedCapabilityId, Method, PciConfig, get_pci_config, get_vf_config};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy<'a> {
+    // Probe every device on every bus.
+    BruteForce,
+    // Start at the given root buses and descend into the secondary bus of each bridge.
+    // Multi-socket systems have a root bus behind each host bridge, not just bus 0.
+    Bridges(&'a [u8]),
+}
+
+#[derive(Clone, Debug)]
+pub struct PciDevice<T: Method> {
+    address: PciAddress,
+    config: PciConfig<T>,
+    physfn: Option<PciAddress>,
+}
+
+impl<T: Method> PciDevice<T> {
+    pub fn new(address: PciAddress, config: PciConfig<T>) -> Self {
+        PciDevice {
+            address,
+            config,
+            physfn: None,
+        }
+    }
+
+    pub fn virtfn(address: PciAddress, config: PciConfig<T>, physfn: PciAddress) -> Self {
+        PciDevice {
+            address,
+            config,
+            physfn: Some(physfn),
+        }
+    }
+
+    pub fn address(&self) -> PciAddress {
+        self.address
+    }
+
+    pub fn config(&self) -> &PciConfig<T> {
+        &self.config
+    }
+
+    // The physical function this SR-IOV virtual function belongs to.
+    pub fn physfn(&self) -> Option<PciAddress> {
+        self.physfn
+    }
+}
+
+pub fn enumerate<T: Method>(segment: u16, strategy: Strategy) -> Vec<PciDevice<T>> {
+    enumerate_with(segment, strategy, T::try_from)
+}
+
+pub fn enumerate_with<T, F>(segment: u16, strategy: Strategy, open: F) -> Vec<PciDevice<T>>
+where
+    T: Method,
+    F: Fn(PciAddress) -> Result<T, error::Error>,
+{
+    let mut devices = vec![];
+
+    match strategy {
+        Strategy::BruteForce => {
+            for bus in 0..=u8::MAX {
+                scan_bus(segment, bus, &open, &mut devices);
+            }
+        }
+        Strategy::Bridges(roots) => {
+            let mut visited = BTreeSet::new();
+            for root in roots {
+                scan_bridges(segment, *root, &open, &mut visited, &mut devices);
+            }
+        }
+    }
+
+    scan_virtfns(&open, &mut devices);
+
+    devices.sort_by_key(|d| d.address);
+    devices
+}
+
+fn scan_bridges<T, F>(
+    segment: u16,
+    bus: u8,
+    open: &F,
+    visited: &mut BTreeSet<u8>,
+    devices: &mut Vec<PciDevice<T>>,
+) where
+    T: Method,
+    F: Fn(PciAddress) -> Result<T, error::Error>,
+{
+    if !visited.insert(bus) {
+        return;
+    }
+
+    let start = devices.len();
+    scan_bus(segment, bus, open, devices);
+
+    let sub_buses: Vec<u8> = devices[start..]
+        .iter()
+        .filter_map(|d| secondary_bus(&d.config))
+        .collect();
+
+    for sub_bus in sub_buses {
+        // An unconfigured or looping bridge reports a secondary bus not above its own.
+        if sub_bus <= bus || sub_bus == u8::MAX {
+            continue;
+        }
+
+        scan_bridges(segment, sub_bus, open, visited, devices);
+    }
+}
+
+pub(crate) fn secondary_bus<T: Method>(config: &PciConfig<T>) -> Option<u8> {
+    config
+        .get_type1_header()
+        .map(|t1| t1.secondary_bus_number())
+        .or_else(|| config.get_type2_header().map(|t2| t2.cardbus_bus_number()))
+}
+
+fn scan_bus<T, F>(segment: u16, bus: u8, open: &F, devices: &mut Vec<PciDevice<T>>)
+where
+    T: Method,
+    F: Fn(PciAddress) -> Result<T, error::Error>,
+{
+    let mut found = BTreeSet::new();
+    for devfn in (0..=u8::MAX).step_by(8) {
+        if found.contains(&devfn) {
+            continue;
+        }
+        let address = PciAddress::from_devfn(segment, bus, devfn);
+        let Some(config) = probe(address, open) else {
+            continue;
+        };
+
+        let multi_function = config.header_type().multi_function_device();
+        let next_function = ari_next_function(&config);
+        devices.push(PciDevice::new(address, config));
+
+        if devfn == 0 {
+            scan_ari(segment, bus, next_function, open, &mut found, devices);
+        }
+
+        if multi_function {
+            for func in 1..8 {
+                if found.contains(&(devfn + func)) {
+                    continue;
+                }
+                let address = PciAddress::from_devfn(segment, bus, devfn + func);
+                if let Some(config) = probe(address, open) {
+                    devices.push(PciDevice::new(address, config));
+                }
+            }
+        }
+    }
+}
+
+// An ARI device numbers its functions 0..=255 and links them by Next Function Number.
+fn scan_ari<T, F>(
+    segment: u16,
+    bus: u8,
+    mut next: Option<u8>,
+    open: &F,
+    found: &mut BTreeSet<u8>,
+    devices: &mut Vec<PciDevice<T>>,
+) where
+    T: Method,
+    F: Fn(PciAddress) -> Result<T, error::Error>,
+{
+    while let Some(function) = next.filter(|f| *f != 0 && found.insert(*f)) {
+        let address = PciAddress::new_ari(segment, bus, function);
+        let Some(config) = probe(address, open) else {
+            break;
+        };
+        next = ari_next_function(&config);
+        devices.push(PciDevice::new(address, config));
+    }
+}
+
+fn ari_next_function<T: Method>(config: &PciConfig<T>) -> Option<u8> {
+    config
+        .extended_capabilities()
+        .find(|c| matches!(c.id(), Some(ExtendedCapabilityId::Ari)))
+        .map(|c| (c.read16(0x04) >> 8) as u8)
+}
+
+// VFs read all-ones at the vendor ID, so they only show up through their PF.
+fn scan_virtfns<T, F>(open: &F, devices: &mut Vec<PciDevice<T>>)
+where
+    T: Method,
+    F: Fn(PciAddress) -> Result<T, error::Error>,
+{
+    let known: BTreeMap<PciAddress, usize> = devices
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (d.address, i))
+        .collect();
+
+    let mut listed = vec![];
+    let mut vfs = vec![];
+    for pf in devices.iter() {
+        for cap in pf.config.extended_capabilities() {
+            let ExtendedCapability::SrIov(sriov) = cap.decode() else {
+                continue;
+            };
+
+            if !sriov.control().vf_enable() {
+                continue;
+            }
+
+            for address in sriov.vf_addresses(pf.address) {
+                // Some backends, like sysfs, already answer for VFs.
+                if let Some(i) = known.get(&address) {
+                    listed.push((*i, pf.address));
+                    continue;
+                }
+
+                let vendor_id = pf.config.vendor_id();
+                let device_id = sriov.vf_device_id();
+                let config = open(address)
+                    .ok()
+                    .and_then(|m| get_vf_config(m, vendor_id, device_id));
+                if let Some(config) = config {
+                    vfs.push(PciDevice::virtfn(address, config, pf.address));
+                }
+            }
+        }
+    }
+
+    for (i, physfn) in listed {
+        devices[i].physfn = Some(physfn);
+    }
+    devices.extend(vfs);
+}
+
+fn probe<T, F>(address: PciAddress, open: &F) -> Option<PciConfig<T>>
+where
+    T: Method,
+    F: Fn(PciAddress) -> Result<T, error::Error>,
+{
+    open(address).ok().and_then(get_pci_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemorySpace;
+    use crate::testing;
+
+    #[test]
+    fn follow_bridges() {
+        let mut space = MemorySpace::new();
+        let functions = [
+            ("00:00.0", testing::bridge(0x8086, 0x80, 0)),
+            ("00:00.1", testing::bridge(0x8086, 0x01, 1)),
+            ("01:00.0", testing::bridge(0x8086, 0x01, 0)),
+            ("01:02.0", testing::bridge(0x8086, 0x01, 1)),
+            ("01:03.0", testing::bridge(0x8086, 0x01, 0xFF)),
+            ("02:00.0", testing::bridge(0x1AF4, 0x00, 0)),
+            // A second host bridge with its own root bus.
+            ("80:00.0", testing::bridge(0x8086, 0x00, 0)),
+            ("80:01.0", testing::bridge(0x8086, 0x01, 0x81)),
+            ("81:00.0", testing::bridge(0x1AF4, 0x00, 0)),
+            ("ff:00.0", testing::bridge(0x1AF4, 0x00, 0)),
+        ];
+        for (address, data) in functions {
+            space.insert(address.parse().unwrap(), &data).unwrap();
+        }
+
+        let open = |a: PciAddress| Ok(space.get(a));
+
+        let devices = enumerate_with(0, Strategy::Bridges(&[0x00, 0x80]), open);
+        let addresses: Vec<String> = devices
+            .iter()
+            .map(|d| format!("{:#}", d.address()))
+            .collect();
+        assert_eq!(
+            addresses,
+            vec![
+                "00:00.0", "00:00.1", "01:00.0", "01:02.0", "01:03.0", "80:00.0", "80:01.0",
+                "81:00.0"
+            ]
+        );
+
+        let devices = enumerate_with(0, Strategy::BruteForce, open);
+        assert_eq!(devices.len(), 10);
+        assert_eq!(devices[9].address(), "ff:00.0".parse().unwrap());
+    }
+
+    #[test]
+    fn ari_functions() {
+        // Each function links to the next in its ARI capability.
+        let ari = |next: u8| {
+            let mut data = testing::function_with_ext_cap(0x000E, 1, &[0x00, next]);
+            data[0x0E] = 0x80;
+            data
+        };
+
+        let mut space = MemorySpace::new();
+        let functions = [
+            (0x00, ari(0x01)),
+            (0x01, ari(0x08)),
+            (0x08, ari(0x92)),
+            (0x92, ari(0x00)),
+        ];
+        for (function, data) in functions {
+            space
+                .insert(PciAddress::new_ari(0, 0x81, function), &data)
+                .unwrap();
+        }
+
+        let devices = enumerate_with(0, Strategy::Bridges(&[0x81]), |a| Ok(space.get(a)));
+        let functions: Vec<u8> = devices.iter().map(|d| d.address().ari_function()).collect();
+        assert_eq!(functions, vec![0x00, 0x01, 0x08, 0x92]);
+    }
+
+    #[test]
+    fn virtual_functions() {
+        let mut pf = testing::bridge(0x8086, 0x00, 0);
+        pf.resize(0x1000, 0);
+        pf[0x02..0x04].copy_from_slice(&0x1521u16.to_le_bytes());
+        pf[0x100..0x104].copy_from_slice(&0x0001_0010u32.to_le_bytes());
+        pf[0x108] = 0x01;
+        pf[0x110] = 3;
+        pf[0x114] = 0x80;
+        pf[0x116] = 0x01;
+        pf[0x11A..0x11C].copy_from_slice(&0x1520u16.to_le_bytes());
+
+        let mut vf = testing::bridge(0xFFFF, 0x00, 0);
+        vf[0x02..0x04].copy_from_slice(&[0xFF, 0xFF]);
+        vf[0x08..0x0C].copy_from_slice(&[0x01, 0x00, 0x00, 0x02]);
+
+        let mut space = MemorySpace::new();
+        space.insert("00:00.0".parse().unwrap(), &pf).unwrap();
+        space.insert("00:10.0".parse().unwrap(), &vf).unwrap();
+        space.insert("00:10.1".parse().unwrap(), &vf).unwrap();
+        // Like sysfs, which fills in the IDs of a VF.
+        space
+            .insert(
+                "00:10.2".parse().unwrap(),
+                &testing::bridge(0x8086, 0x00, 0),
+            )
+            .unwrap();
+
+        let devices = enumerate_with(0, Strategy::Bridges(&[0]), |a| Ok(space.get(a)));
+        let addresses: Vec<String> = devices
+            .iter()
+            .map(|d| format!("{:#}", d.address()))
+            .collect();
+        assert_eq!(addresses, vec!["00:00.0", "00:10.0", "00:10.1", "00:10.2"]);
+
+        let pf = devices[0].address();
+        assert!(devices[0].physfn().is_none());
+        assert!(devices[1..].iter().all(|d| d.physfn() == Some(pf)));
+        assert_eq!(devices[1].config().vendor_id(), 0x8086);
+        assert_eq!(devices[1].config().device_id(), 0x1520);
+        assert_eq!(devices[1].config().class_code().base_class(), 0x02);
+    }
+}
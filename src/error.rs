@@ -2,10 +2,22 @@ use nom;
 
 #[derive(Debug)]
 pub enum Error {
+    CapabilityLoop(u16),
+    InvalidAddress(String),
+    InvalidCapabilityPointer(u16),
+    InvalidLength(usize),
+    InvalidRomImage(u32),
     Io(std::io::Error),
+    Misaligned(u16),
     NotFoundAcpiMcfg,
+    NotFoundAcpiMcfgSpace,
+    OutOfRange(u16),
     Parse(String),
+    ReadOnly,
     TrailingData,
+    UnsupportedPowerState,
+    UnsupportedRomAccess,
+    UnsupportedSegment(u16),
 }
 
 impl From<std::io::Error> for Error {
@@ -1,39 +1,276 @@
+use super::error::Error;
 use super::parser;
+use std::borrow::Cow;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(not(feature = "static-ids"))]
 use std::sync::OnceLock;
 
-const PCIIDS: &str = include_str!("pciids/pci.ids");
+#[cfg(all(feature = "compressed-ids", not(feature = "static-ids")))]
+use flate2::read::DeflateDecoder;
+#[cfg(all(feature = "compressed-ids", not(feature = "static-ids")))]
+use std::io::Read;
+
+const EMBEDDED: bool = cfg!(any(
+    feature = "embedded-ids",
+    feature = "compressed-ids",
+    feature = "static-ids"
+));
+
+// Overrides the system locations when set.
+pub const PCI_IDS_ENV: &str = "PCI_IDS";
+
+pub const SYSTEM_PATHS: &[&str] = &[
+    "/usr/share/hwdata/pci.ids",
+    "/usr/share/misc/pci.ids",
+    "/usr/share/pci.ids",
+];
+
+#[cfg(not(feature = "static-ids"))]
+static DATABASE: OnceLock<Database> = OnceLock::new();
+
+// Tables generated from pci.ids by the build script.
+#[cfg(feature = "static-ids")]
+include!(concat!(env!("OUT_DIR"), "/pci_ids.rs"));
+
+// The embedded database, parsed on first use.
+// Without one, this is whatever `Database::load` finds at that point.
+#[cfg(not(feature = "static-ids"))]
+pub fn database() -> &'static Database {
+    DATABASE.get_or_init(|| match embedded_ids() {
+        Some(text) => Database::parse(&text).unwrap(),
+        None => Database::load().into_owned(),
+    })
+}
+
+#[cfg(all(
+    feature = "embedded-ids",
+    not(feature = "compressed-ids"),
+    not(feature = "static-ids")
+))]
+fn embedded_ids() -> Option<Cow<'static, str>> {
+    Some(Cow::Borrowed(include_str!("pciids/pci.ids")))
+}
 
-static VENDORS: OnceLock<Vec<Vendor>> = OnceLock::new();
+#[cfg(all(feature = "compressed-ids", not(feature = "static-ids")))]
+fn embedded_ids() -> Option<Cow<'static, str>> {
+    const COMPRESSED: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/pci.ids.deflate"));
 
-static CLASSES: OnceLock<Vec<BaseClass>> = OnceLock::new();
+    let mut text = String::new();
+    DeflateDecoder::new(COMPRESSED)
+        .read_to_string(&mut text)
+        .unwrap();
+    Some(Cow::Owned(text))
+}
+
+#[cfg(not(any(
+    feature = "embedded-ids",
+    feature = "compressed-ids",
+    feature = "static-ids"
+)))]
+fn embedded_ids() -> Option<Cow<'static, str>> {
+    None
+}
+
+#[cfg(feature = "static-ids")]
+pub fn database() -> &'static Database {
+    &DATABASE
+}
 
 pub fn get_vendor(id: u16) -> Option<&'static Vendor> {
-    VENDORS.get_or_init(init_vendor).iter().find(|v| v.id == id)
+    database().get_vendor(id)
 }
 
 pub fn get_class(id: u8) -> Option<&'static BaseClass> {
-    CLASSES.get_or_init(init_class).iter().find(|c| c.id == id)
+    database().get_class(id)
 }
 
-fn init_vendor() -> Vec<Vendor> {
-    let (v, _) = parser::parse(PCIIDS).unwrap();
-    v
+// Every list is kept sorted by ID, so lookups are binary searches.
+// With duplicate IDs the first entry in file order wins, as with a linear scan.
+fn find<T, K: Ord>(items: &[T], key: K, id: impl Fn(&T) -> K) -> Option<&T> {
+    let i = items.partition_point(|item| id(item) < key);
+    items.get(i).filter(|item| id(item) == key)
+}
+
+// Case-insensitive name pattern. `*` matches any run of characters and `?` a single one.
+// A pattern without either matches any name that contains it.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    chars: Vec<char>,
+    wildcard: bool,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Self {
+        Pattern {
+            chars: pattern.to_lowercase().chars().collect(),
+            wildcard: pattern.contains(['*', '?']),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        let name: Vec<char> = name.to_lowercase().chars().collect();
+        if self.wildcard {
+            wildcard_match(&self.chars, &name)
+        } else {
+            self.chars.is_empty() || name.windows(self.chars.len()).any(|w| w == self.chars)
+        }
+    }
+}
+
+// Backtracks to the last `*` on a mismatch, so it runs without recursion.
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((sp, sn)) => {
+                    p = sp + 1;
+                    n = sn + 1;
+                    star = Some((sp, sn + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
 }
 
-fn init_class() -> Vec<BaseClass> {
-    let (_, c) = parser::parse(PCIIDS).unwrap();
-    c
+#[derive(Clone, Debug, Default)]
+pub struct Database {
+    vendors: Cow<'static, [Vendor]>,
+    classes: Cow<'static, [BaseClass]>,
 }
 
+impl Database {
+    pub fn new(mut vendors: Vec<Vendor>, mut classes: Vec<BaseClass>) -> Self {
+        vendors.sort_by_key(|v| v.id);
+        classes.sort_by_key(|c| c.id);
+        Database {
+            vendors: Cow::Owned(vendors),
+            classes: Cow::Owned(classes),
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let (vendors, classes) = parser::parse(input)?;
+        Ok(Database::new(vendors, classes))
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Database::parse(&fs::read_to_string(path)?)
+    }
+
+    // None if the crate was built without any of the ID features.
+    pub fn embedded() -> Option<&'static Self> {
+        EMBEDDED.then(database)
+    }
+
+    // The first readable file of `PCI_IDS` and the system locations wins.
+    // A missing or broken file is skipped rather than reported.
+    // Falls back to the embedded database, which is borrowed, and then to an empty one.
+    pub fn load() -> Cow<'static, Self> {
+        Database::search_paths()
+            .iter()
+            .find_map(|path| Database::open(path).ok())
+            .map(Cow::Owned)
+            .or_else(|| Database::embedded().map(Cow::Borrowed))
+            .unwrap_or_default()
+    }
+
+    pub fn search_paths() -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = env::var_os(PCI_IDS_ENV)
+            .map(PathBuf::from)
+            .into_iter()
+            .collect();
+        paths.extend(SYSTEM_PATHS.iter().map(PathBuf::from));
+        paths
+    }
+
+    pub fn vendors(&self) -> &[Vendor] {
+        &self.vendors
+    }
+
+    pub fn classes(&self) -> &[BaseClass] {
+        &self.classes
+    }
+
+    pub fn get_vendor(&self, id: u16) -> Option<&Vendor> {
+        find(&self.vendors, id, |v| v.id)
+    }
+
+    pub fn get_class(&self, id: u8) -> Option<&BaseClass> {
+        find(&self.classes, id, |c| c.id)
+    }
+
+    pub fn search_vendors<'a>(
+        &'a self,
+        pattern: &str,
+    ) -> impl Iterator<Item = &'a Vendor> + use<'a> {
+        let pattern = Pattern::new(pattern);
+        self.vendors
+            .iter()
+            .filter(move |v| pattern.matches(&v.name))
+    }
+
+    // Devices of every vendor, for when the vendor isn't known yet.
+    pub fn search_devices<'a>(
+        &'a self,
+        pattern: &str,
+    ) -> impl Iterator<Item = (&'a Vendor, &'a Device)> + use<'a> {
+        let pattern = Pattern::new(pattern);
+        self.vendors.iter().flat_map(move |v| {
+            let pattern = pattern.clone();
+            v.devices
+                .iter()
+                .filter(move |d| pattern.matches(&d.name))
+                .map(move |d| (v, d))
+        })
+    }
+
+    // pci.ids doesn't say which class a device belongs to, so this only finds class IDs by name.
+    pub fn search_sub_classes<'a>(
+        &'a self,
+        pattern: &str,
+    ) -> impl Iterator<Item = (&'a BaseClass, &'a SubClass)> + use<'a> {
+        let pattern = Pattern::new(pattern);
+        self.classes.iter().flat_map(move |c| {
+            let pattern = pattern.clone();
+            c.sub_classes
+                .iter()
+                .filter(move |s| pattern.matches(&s.name))
+                .map(move |s| (c, s))
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Vendor {
     id: u16,
-    name: String,
-    devices: Vec<Device>,
+    name: Cow<'static, str>,
+    devices: Cow<'static, [Device]>,
 }
 
 impl Vendor {
-    pub fn new(id: u16, name: String, devices: Vec<Device>) -> Self {
-        Vendor { id, name, devices }
+    pub fn new(id: u16, name: String, mut devices: Vec<Device>) -> Self {
+        devices.sort_by_key(|d| d.id);
+        Vendor {
+            id,
+            name: Cow::Owned(name),
+            devices: Cow::Owned(devices),
+        }
     }
 
     pub fn id(&self) -> u16 {
@@ -41,26 +278,42 @@ impl Vendor {
     }
 
     pub fn name(&self) -> &str {
-        self.name.as_str()
+        &self.name
+    }
+
+    pub fn devices(&self) -> &[Device] {
+        &self.devices
     }
 
     pub fn get_device(&self, id: u16) -> Option<&Device> {
-        self.devices.iter().find(|d| d.id == id)
+        find(&self.devices, id, |d| d.id)
+    }
+
+    pub fn search_devices<'a>(
+        &'a self,
+        pattern: &str,
+    ) -> impl Iterator<Item = &'a Device> + use<'a> {
+        let pattern = Pattern::new(pattern);
+        self.devices
+            .iter()
+            .filter(move |d| pattern.matches(&d.name))
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct Device {
     id: u16,
-    name: String,
-    subsystems: Vec<SubSystem>,
+    name: Cow<'static, str>,
+    subsystems: Cow<'static, [SubSystem]>,
 }
 
 impl Device {
-    pub fn new(id: u16, name: String, subsystems: Vec<SubSystem>) -> Self {
+    pub fn new(id: u16, name: String, mut subsystems: Vec<SubSystem>) -> Self {
+        subsystems.sort_by_key(|s| (s.sub_vendor, s.sub_device));
         Device {
             id,
-            name,
-            subsystems,
+            name: Cow::Owned(name),
+            subsystems: Cow::Owned(subsystems),
         }
     }
 
@@ -69,20 +322,25 @@ impl Device {
     }
 
     pub fn name(&self) -> &str {
-        self.name.as_str()
+        &self.name
+    }
+
+    pub fn subsystems(&self) -> &[SubSystem] {
+        &self.subsystems
     }
 
     pub fn get_subsystem(&self, vendor: u16, device: u16) -> Option<&SubSystem> {
-        self.subsystems
-            .iter()
-            .find(|s| s.sub_vendor == vendor && s.sub_device == device)
+        find(&self.subsystems, (vendor, device), |s| {
+            (s.sub_vendor, s.sub_device)
+        })
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct SubSystem {
     sub_vendor: u16,
     sub_device: u16,
-    name: String,
+    name: Cow<'static, str>,
 }
 
 impl SubSystem {
@@ -90,7 +348,7 @@ impl SubSystem {
         SubSystem {
             sub_vendor,
             sub_device,
-            name,
+            name: Cow::Owned(name),
         }
     }
 
@@ -103,22 +361,24 @@ impl SubSystem {
     }
 
     pub fn name(&self) -> &str {
-        self.name.as_str()
+        &self.name
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct BaseClass {
     id: u8,
-    name: String,
-    sub_classes: Vec<SubClass>,
+    name: Cow<'static, str>,
+    sub_classes: Cow<'static, [SubClass]>,
 }
 
 impl BaseClass {
-    pub fn new(id: u8, name: String, sub_classes: Vec<SubClass>) -> Self {
+    pub fn new(id: u8, name: String, mut sub_classes: Vec<SubClass>) -> Self {
+        sub_classes.sort_by_key(|c| c.id);
         BaseClass {
             id,
-            name,
-            sub_classes,
+            name: Cow::Owned(name),
+            sub_classes: Cow::Owned(sub_classes),
         }
     }
 
@@ -127,23 +387,43 @@ impl BaseClass {
     }
 
     pub fn name(&self) -> &str {
-        self.name.as_str()
+        &self.name
+    }
+
+    pub fn sub_classes(&self) -> &[SubClass] {
+        &self.sub_classes
     }
 
     pub fn get_sub_class(&self, id: u8) -> Option<&SubClass> {
-        self.sub_classes.iter().find(|c| c.id == id)
+        find(&self.sub_classes, id, |c| c.id)
+    }
+
+    pub fn search_sub_classes<'a>(
+        &'a self,
+        pattern: &str,
+    ) -> impl Iterator<Item = &'a SubClass> + use<'a> {
+        let pattern = Pattern::new(pattern);
+        self.sub_classes
+            .iter()
+            .filter(move |s| pattern.matches(&s.name))
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct SubClass {
     id: u8,
-    name: String,
-    prog_ifs: Vec<ProgIf>,
+    name: Cow<'static, str>,
+    prog_ifs: Cow<'static, [ProgIf]>,
 }
 
 impl SubClass {
-    pub fn new(id: u8, name: String, prog_ifs: Vec<ProgIf>) -> Self {
-        SubClass { id, name, prog_ifs }
+    pub fn new(id: u8, name: String, mut prog_ifs: Vec<ProgIf>) -> Self {
+        prog_ifs.sort_by_key(|p| p.id);
+        SubClass {
+            id,
+            name: Cow::Owned(name),
+            prog_ifs: Cow::Owned(prog_ifs),
+        }
     }
 
     pub fn id(&self) -> u8 {
@@ -151,22 +431,30 @@ impl SubClass {
     }
 
     pub fn name(&self) -> &str {
-        self.name.as_str()
+        &self.name
+    }
+
+    pub fn prog_ifs(&self) -> &[ProgIf] {
+        &self.prog_ifs
     }
 
     pub fn get_prog_if(&self, id: u8) -> Option<&ProgIf> {
-        self.prog_ifs.iter().find(|c| c.id == id)
+        find(&self.prog_ifs, id, |p| p.id)
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct ProgIf {
     id: u8,
-    name: String,
+    name: Cow<'static, str>,
 }
 
 impl ProgIf {
     pub fn new(id: u8, name: String) -> Self {
-        ProgIf { id, name }
+        ProgIf {
+            id,
+            name: Cow::Owned(name),
+        }
     }
 
     pub fn id(&self) -> u8 {
@@ -174,6 +462,154 @@ impl ProgIf {
     }
 
     pub fn name(&self) -> &str {
-        self.name.as_str()
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_file() {
+        let path = env::temp_dir().join(format!("pci-ids-{}", std::process::id()));
+        fs::write(
+            &path,
+            "# local\n1234  Acme\n\t5678  Widget\n\t\t1234 0001  Widget Pro\nC 02  Network controller\n\t00  Ethernet controller\n",
+        )
+        .unwrap();
+
+        let db = Database::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let vendor = db.get_vendor(0x1234).unwrap();
+        assert_eq!(vendor.name(), "Acme");
+        let device = vendor.get_device(0x5678).unwrap();
+        assert_eq!(device.name(), "Widget");
+        assert_eq!(
+            device.get_subsystem(0x1234, 0x0001).unwrap().name(),
+            "Widget Pro"
+        );
+        assert!(db.get_vendor(0x8086).is_none());
+        assert_eq!(
+            db.get_class(0x02)
+                .unwrap()
+                .get_sub_class(0x00)
+                .unwrap()
+                .name(),
+            "Ethernet controller"
+        );
+        assert_eq!(db.vendors().len(), 1);
+
+        assert!(Database::open(&path).is_err());
+        assert!(Database::parse("not a database").is_err());
+        assert_eq!(
+            Database::search_paths().last().unwrap(),
+            Path::new("/usr/share/pci.ids")
+        );
+    }
+
+    #[test]
+    #[cfg(any(
+        feature = "embedded-ids",
+        feature = "compressed-ids",
+        feature = "static-ids"
+    ))]
+    fn embedded() {
+        let intel = get_vendor(0x8086).unwrap();
+        assert_eq!(intel.name(), "Intel Corporation");
+        assert!(intel.get_device(0x100e).is_some());
+        assert!(get_class(0x02).unwrap().get_sub_class(0x00).is_some());
+
+        // The build script has to produce the same tables as the runtime parser.
+        let content = fs::read_to_string("src/pciids/pci.ids").unwrap();
+        let parsed = Database::parse(&content).unwrap();
+        let db = database();
+        assert_eq!(parsed.vendors().len(), db.vendors().len());
+        assert_eq!(parsed.classes().len(), db.classes().len());
+        for (p, v) in parsed.vendors().iter().zip(db.vendors()) {
+            assert_eq!((p.id(), p.name()), (v.id(), v.name()));
+            assert_eq!(p.devices().len(), v.devices().len());
+            for (p, d) in p.devices().iter().zip(v.devices()) {
+                assert_eq!((p.id(), p.name()), (d.id(), d.name()));
+                assert_eq!(p.subsystems().len(), d.subsystems().len());
+            }
+        }
+    }
+
+    #[test]
+    fn lookup_unsorted() {
+        let devices = vec![
+            Device::new(0x0003, "c".to_string(), vec![]),
+            Device::new(0x0001, "a".to_string(), vec![]),
+            Device::new(0x0002, "b".to_string(), vec![]),
+            Device::new(0x0001, "a2".to_string(), vec![]),
+        ];
+        let subsystems = vec![
+            SubSystem::new(0x1AF4, 0x0001, "y".to_string()),
+            SubSystem::new(0x1028, 0x0002, "x".to_string()),
+        ];
+        let vendors = vec![
+            Vendor::new(0x8086, "Intel".to_string(), devices),
+            Vendor::new(0x1028, "Dell".to_string(), vec![]),
+            Vendor::new(
+                0x1AF4,
+                "Red Hat".to_string(),
+                vec![Device::new(0x1000, "net".to_string(), subsystems)],
+            ),
+        ];
+        let db = Database::new(vendors, vec![]);
+
+        let ids: Vec<u16> = db.vendors().iter().map(|v| v.id()).collect();
+        assert_eq!(ids, vec![0x1028, 0x1AF4, 0x8086]);
+        assert!(db.get_vendor(0x0000).is_none());
+        assert!(db.get_vendor(0xFFFF).is_none());
+
+        let intel = db.get_vendor(0x8086).unwrap();
+        assert_eq!(intel.get_device(0x0001).unwrap().name(), "a");
+        assert_eq!(intel.get_device(0x0003).unwrap().name(), "c");
+        assert!(intel.get_device(0x0004).is_none());
+
+        let net = db.get_vendor(0x1AF4).unwrap().get_device(0x1000).unwrap();
+        assert_eq!(net.get_subsystem(0x1AF4, 0x0001).unwrap().name(), "y");
+        assert_eq!(net.get_subsystem(0x1028, 0x0002).unwrap().name(), "x");
+        assert!(net.get_subsystem(0x1028, 0x0001).is_none());
+    }
+
+    #[test]
+    fn search() {
+        let db = Database::parse(
+            "8086  Intel Corporation\n\t100e  82540EM Gigabit Ethernet Controller\n\t10d3  82574L Gigabit Network Connection\n\t1237  440FX - 82441FX PMC [Natoma]\n14e4  Broadcom Inc. and subsidiaries\n\t1657  NetXtreme BCM5719 Gigabit Ethernet PCIe\nC 02  Network controller\n\t00  Ethernet controller\n\t80  Network controller\nC 06  Bridge\n\t00  Host bridge\n",
+        )
+        .unwrap();
+
+        let vendors = |p: &str| -> Vec<u16> { db.search_vendors(p).map(|v| v.id()).collect() };
+        assert_eq!(vendors("intel"), vec![0x8086]);
+        assert_eq!(vendors("*INC*"), vec![0x14e4]);
+        assert_eq!(vendors("*corporation"), vec![0x8086]);
+        // Wildcards anchor the pattern to the whole name.
+        assert!(vendors("int?l").is_empty());
+        assert_eq!(vendors("int?l*"), vec![0x8086]);
+        assert_eq!(db.search_vendors("").count(), 2);
+
+        let intel = db.get_vendor(0x8086).unwrap();
+        let ids: Vec<u16> = intel.search_devices("gigabit").map(|d| d.id()).collect();
+        assert_eq!(ids, vec![0x100e, 0x10d3]);
+        let ids: Vec<u16> = intel.search_devices("*[natoma]").map(|d| d.id()).collect();
+        assert_eq!(ids, vec![0x1237]);
+
+        let ids: Vec<(u16, u16)> = db
+            .search_devices("gigabit ethernet")
+            .map(|(v, d)| (v.id(), d.id()))
+            .collect();
+        assert_eq!(ids, vec![(0x14e4, 0x1657), (0x8086, 0x100e)]);
+
+        let ids: Vec<(u8, u8)> = db
+            .search_sub_classes("ethernet")
+            .map(|(c, s)| (c.id(), s.id()))
+            .collect();
+        assert_eq!(ids, vec![(0x02, 0x00)]);
+        let network = db.get_class(0x02).unwrap();
+        assert_eq!(network.search_sub_classes("*controller").count(), 2);
     }
 }
@@ -1,5 +1,5 @@
-use super::Method;
 use super::error;
+use super::{CONFIG_SPACE_SIZE, Method, PciAddress};
 use std::arch::asm;
 
 const CONFIG_ADDRESS: u16 = 0x0CF8;
@@ -13,29 +13,81 @@ pub struct IoPort {
 }
 
 impl Method for IoPort {
-    fn try_from(bus: u8, device: u8, func: u8) -> Result<Self, error::Error> {
-        Ok(IoPort { bus, device, func })
+    fn try_from(address: PciAddress) -> Result<Self, error::Error> {
+        // CONFIG_ADDRESS can reach only the first segment.
+        if address.domain() != 0 {
+            return Err(error::Error::UnsupportedSegment(address.domain()));
+        }
+
+        Ok(IoPort {
+            bus: address.bus(),
+            device: address.device(),
+            func: address.function(),
+        })
+    }
+
+    // CONFIG_ADDRESS can not reach the extended configuration space.
+    fn config_size(&self) -> u16 {
+        CONFIG_SPACE_SIZE
     }
 
-    fn read8(&self, offset: u8) -> u8 {
+    fn read8(&self, offset: u16) -> u8 {
         let (addr, shift) = multiple4(offset);
         let value = self.read32(addr);
         ((value >> shift) & 0x0000_00FF) as u8
     }
 
-    fn read16(&self, offset: u8) -> u16 {
+    fn read16(&self, offset: u16) -> u16 {
         let (addr, shift) = multiple4(offset);
         let value = self.read32(addr);
         ((value >> shift) & 0x0000_FFFF) as u16
     }
 
-    fn read32(&self, offset: u8) -> u32 {
-        set_config(self.bus, self.device, self.func, offset);
+    fn read32(&self, offset: u16) -> u32 {
+        if offset >= CONFIG_SPACE_SIZE {
+            return 0xFFFF_FFFF;
+        }
+
+        set_config(self.bus, self.device, self.func, offset as u8);
         read32(CONFIG_DATA)
     }
+
+    fn write8(&self, offset: u16, value: u8) -> Result<(), error::Error> {
+        check_access(offset, 1)?;
+        let (addr, shift) = multiple4(offset);
+        set_config(self.bus, self.device, self.func, addr as u8);
+        write8(CONFIG_DATA + shift / 8, value);
+        Ok(())
+    }
+
+    fn write16(&self, offset: u16, value: u16) -> Result<(), error::Error> {
+        check_access(offset, 2)?;
+        let (addr, shift) = multiple4(offset);
+        set_config(self.bus, self.device, self.func, addr as u8);
+        write16(CONFIG_DATA + shift / 8, value);
+        Ok(())
+    }
+
+    fn write32(&self, offset: u16, value: u32) -> Result<(), error::Error> {
+        check_access(offset, 4)?;
+        set_config(self.bus, self.device, self.func, offset as u8);
+        write32(CONFIG_DATA, value);
+        Ok(())
+    }
 }
 
-fn multiple4(value: u8) -> (u8, u8) {
+// A write must stay within one dword of CONFIG_DATA.
+fn check_access(offset: u16, size: u16) -> Result<(), error::Error> {
+    if offset >= CONFIG_SPACE_SIZE {
+        Err(error::Error::OutOfRange(offset))
+    } else if offset % size != 0 {
+        Err(error::Error::Misaligned(offset))
+    } else {
+        Ok(())
+    }
+}
+
+fn multiple4(value: u16) -> (u16, u16) {
     let r = value % 4;
     (value - r, r * 8)
 }
@@ -64,7 +116,6 @@ fn read8(address: u16) -> u8 {
     ret
 }
 
-#[allow(dead_code)]
 fn write8(address: u16, value: u8) {
     unsafe {
         asm!(
@@ -90,7 +141,6 @@ fn read16(address: u16) -> u16 {
     ret
 }
 
-#[allow(dead_code)]
 fn write16(address: u16, value: u16) {
     unsafe {
         asm!(
@@ -125,3 +175,27 @@ fn write32(address: u16, value: u32) {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_access() {
+        assert!(check_access(0x3C, 4).is_ok());
+        assert!(check_access(0x3E, 2).is_ok());
+        assert!(check_access(0x3F, 1).is_ok());
+        assert!(matches!(
+            check_access(0x3F, 2),
+            Err(error::Error::Misaligned(0x3F))
+        ));
+        assert!(matches!(
+            check_access(0x3E, 4),
+            Err(error::Error::Misaligned(0x3E))
+        ));
+        assert!(matches!(
+            check_access(0x100, 4),
+            Err(error::Error::OutOfRange(0x100))
+        ));
+    }
+}
@@ -0,0 +1,232 @@
+use super::Method;
+use super::acs::{AcsCapability, ISOLATION_FLAGS};
+use super::address::PciAddress;
+use super::capability::{Capability, ExtendedCapability};
+use super::enumerate::{PciDevice, secondary_bus};
+use super::pcie::DevicePortType;
+use std::collections::BTreeMap;
+
+struct Node<T: Method> {
+    address: PciAddress,
+    bus: u8,
+    secondary_bus: Option<u8>,
+    port_type: Option<DevicePortType>,
+    acs: Option<AcsCapability<T>>,
+    multifunction: bool,
+}
+
+impl<T: Method> Node<T> {
+    fn from(device: &PciDevice<T>) -> Self {
+        let config = device.config();
+        let address = device.address();
+
+        let port_type = config.capabilities().find_map(|c| match c.decode() {
+            Capability::PciE(pcie) => Some(pcie.device_port_type()),
+            _ => None,
+        });
+        let acs = config
+            .extended_capabilities()
+            .find_map(|c| match c.decode() {
+                ExtendedCapability::Acs(acs) => Some(acs),
+                _ => None,
+            });
+
+        // VFs hang off the bus of their PF, whatever bus number they were given.
+        let bus = device.physfn().unwrap_or(address).bus();
+
+        Node {
+            address,
+            bus,
+            secondary_bus: secondary_bus(config).filter(|b| *b > address.bus()),
+            port_type,
+            acs,
+            multifunction: device.physfn().is_none()
+                && config.header_type().multi_function_device(),
+        }
+    }
+
+    // Same rules as the Linux kernel's pci_acs_enabled().
+    fn isolated(&self) -> bool {
+        let Some(port_type) = self.port_type else {
+            // Everything on a conventional bus can see everyone else's DMA.
+            return false;
+        };
+
+        match port_type {
+            DevicePortType::PciExpressToPciBridge
+            | DevicePortType::PciToPciExpressBridge
+            | DevicePortType::RootComplexEventCollector => false,
+            DevicePortType::RootPort | DevicePortType::DownstreamPort => self.acs_enabled(),
+            DevicePortType::Endpoint
+            | DevicePortType::LegacyEndpoint
+            | DevicePortType::UpstreamPort
+            | DevicePortType::RootComplexIntegratedEndpoint
+                if self.multifunction =>
+            {
+                self.acs_enabled()
+            }
+            _ => true,
+        }
+    }
+
+    fn acs_enabled(&self) -> bool {
+        self.acs
+            .as_ref()
+            .is_some_and(|acs| acs.enabled(ISOLATION_FLAGS))
+    }
+}
+
+// Splits the functions into groups that can't reach each other with peer-to-peer DMA,
+// following how Linux forms IOMMU groups. Only a whole group can safely be passed through.
+pub fn isolation_groups<T: Method>(devices: &[PciDevice<T>]) -> Vec<Vec<PciAddress>> {
+    let nodes: Vec<Node<T>> = devices.iter().map(Node::from).collect();
+
+    let bridges: BTreeMap<(u16, u8), usize> = nodes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, n)| n.secondary_bus.map(|b| ((n.address.domain(), b), i)))
+        .collect();
+    let upstream = |i: usize| {
+        bridges
+            .get(&(nodes[i].address.domain(), nodes[i].bus))
+            .copied()
+    };
+
+    // Bridges always sit on a lower bus than their secondary bus, so these walks end.
+    let path_isolated = |mut i: usize| loop {
+        if !nodes[i].isolated() {
+            return false;
+        }
+        match upstream(i) {
+            Some(bridge) => i = bridge,
+            None => return true,
+        }
+    };
+
+    let mut groups: Vec<usize> = (0..nodes.len()).collect();
+    for i in 0..nodes.len() {
+        // A function shares the group of the highest bridge that doesn't isolate it.
+        let mut alias = i;
+        let mut bridge = upstream(i);
+        while let Some(b) = bridge {
+            if path_isolated(b) {
+                break;
+            }
+            alias = b;
+            bridge = upstream(b);
+        }
+        union(&mut groups, i, alias);
+
+        // Functions of one device without ACS may talk to each other internally.
+        let node = &nodes[alias];
+        if node.multifunction && !node.isolated() {
+            for (j, other) in nodes.iter().enumerate() {
+                if j != alias
+                    && other.address.domain() == node.address.domain()
+                    && other.address.bus() == node.address.bus()
+                    && other.address.device() == node.address.device()
+                    && !other.isolated()
+                {
+                    union(&mut groups, alias, j);
+                }
+            }
+        }
+    }
+
+    let mut sets: BTreeMap<usize, Vec<PciAddress>> = BTreeMap::new();
+    for (i, node) in nodes.iter().enumerate() {
+        sets.entry(find(&mut groups, i))
+            .or_default()
+            .push(node.address);
+    }
+
+    let mut result: Vec<Vec<PciAddress>> = sets
+        .into_values()
+        .map(|mut group| {
+            group.sort();
+            group
+        })
+        .collect();
+    result.sort();
+    result
+}
+
+fn find(groups: &mut [usize], mut i: usize) -> usize {
+    while groups[i] != i {
+        groups[i] = groups[groups[i]];
+        i = groups[i];
+    }
+    i
+}
+
+fn union(groups: &mut [usize], a: usize, b: usize) {
+    let a = find(groups, a);
+    let b = find(groups, b);
+    groups[a.max(b)] = a.min(b);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::Memory;
+    use crate::testing;
+
+    #[test]
+    fn groups() {
+        let (endpoint, root_port, upstream, downstream) = (0x0, 0x4, 0x5, 0x6);
+        let functions = [
+            ("00:00.0", testing::port(0x00, 0, None, None)),
+            (
+                "00:01.0",
+                testing::port(0x01, 1, Some(root_port), Some(0x001D)),
+            ),
+            ("01:00.0", testing::port(0x80, 0, Some(endpoint), None)),
+            ("01:00.1", testing::port(0x80, 0, Some(endpoint), None)),
+            ("00:02.0", testing::port(0x01, 2, Some(root_port), None)),
+            ("02:00.0", testing::port(0x00, 0, Some(endpoint), None)),
+            (
+                "00:03.0",
+                testing::port(0x01, 3, Some(root_port), Some(0x001D)),
+            ),
+            ("03:00.0", testing::port(0x01, 4, Some(upstream), None)),
+            (
+                "04:00.0",
+                testing::port(0x01, 5, Some(downstream), Some(0x001D)),
+            ),
+            (
+                "04:01.0",
+                testing::port(0x01, 6, Some(downstream), Some(0x0001)),
+            ),
+            ("05:00.0", testing::port(0x00, 0, Some(endpoint), None)),
+            ("06:00.0", testing::port(0x00, 0, Some(endpoint), None)),
+            ("00:1e.0", testing::port(0x01, 7, None, None)),
+            ("07:00.0", testing::port(0x00, 0, None, None)),
+            ("07:01.0", testing::port(0x00, 0, None, None)),
+        ];
+
+        let devices: Vec<PciDevice<Memory>> = functions
+            .iter()
+            .map(|(address, data)| PciDevice::new(address.parse().unwrap(), testing::config(data)))
+            .collect();
+
+        let groups: Vec<Vec<String>> = isolation_groups(&devices)
+            .iter()
+            .map(|g| g.iter().map(|a| format!("{a:#}")).collect())
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                vec!["00:00.0"],
+                vec!["00:01.0"],
+                vec!["00:02.0", "02:00.0"],
+                vec!["00:03.0"],
+                vec!["00:1e.0", "07:00.0", "07:01.0"],
+                vec!["01:00.0", "01:00.1"],
+                vec!["03:00.0"],
+                vec!["04:00.0"],
+                vec!["04:01.0", "06:00.0"],
+                vec!["05:00.0"],
+            ]
+        );
+    }
+}
@@ -1,50 +1,104 @@
+pub mod acs;
+pub mod address;
+pub mod aer;
+pub mod capability;
+pub mod dump;
+pub mod enumerate;
 pub mod error;
 pub mod ids;
 pub mod io_port;
+pub mod isolation;
+pub mod memory;
+pub mod msi;
 pub mod parser;
+pub mod pcie;
+pub mod pm;
+pub mod rom;
+pub mod sriov;
+
+#[cfg(test)]
+mod testing;
 
 #[cfg(target_family = "unix")]
 pub mod ecam;
 
+#[cfg(target_os = "linux")]
+pub mod sysfs;
+
 #[cfg(target_family = "windows")]
 pub mod cfgmgr32;
 
+use std::collections::BTreeSet;
 use std::rc::Rc;
 
-pub const OFFSET_VENDOR_ID: u8 = 0x00;
-pub const OFFSET_DEVICE_ID: u8 = 0x02;
-pub const OFFSET_COMMAND: u8 = 0x04;
-pub const OFFSET_STATUS: u8 = 0x06;
-pub const OFFSET_REVISION_ID: u8 = 0x08;
-pub const OFFSET_PROG_INTF: u8 = 0x09;
-pub const OFFSET_SUB_CLASS: u8 = 0x0A;
-pub const OFFSET_BASE_CLASS: u8 = 0x0B;
-pub const OFFSET_CACHE_LINE_SIZE: u8 = 0x0C;
-pub const OFFSET_MASTER_LATENCY_TIMER: u8 = 0x0D;
-pub const OFFSET_HEADER_TYPE: u8 = 0x0E;
-pub const OFFSET_BIST: u8 = 0x0F;
-pub const OFFSET_CAPABILITIES_POINTER: u8 = 0x34;
-pub const OFFSET_INTERRUPT_LINE: u8 = 0x3C;
-pub const OFFSET_INTERRUPT_PIN: u8 = 0x3D;
-
-pub const OFFSET_TYPE0_BAR0: u8 = 0x10;
-pub const OFFSET_TYPE0_BAR1: u8 = 0x14;
-pub const OFFSET_TYPE0_BAR2: u8 = 0x18;
-pub const OFFSET_TYPE0_BAR3: u8 = 0x1C;
-pub const OFFSET_TYPE0_BAR4: u8 = 0x20;
-pub const OFFSET_TYPE0_BAR5: u8 = 0x24;
-pub const OFFSET_TYPE0_CARDBUS: u8 = 0x28;
-pub const OFFSET_TYPE0_SUBSYSTEM_VENDOR_ID: u8 = 0x2C;
-pub const OFFSET_TYPE0_SUBSYSTEM_ID: u8 = 0x2E;
-pub const OFFSET_TYPE0_EXPANSION: u8 = 0x30;
-
-pub const OFFSET_TYPE1_BAR0: u8 = 0x10;
-pub const OFFSET_TYPE1_BAR1: u8 = 0x14;
-pub const OFFSET_TYPE1_PRIMARY_BUS_NUM: u8 = 0x18;
-pub const OFFSET_TYPE1_SECONDARY_BUS_NUM: u8 = 0x19;
-pub const OFFSET_TYPE1_SUBORDINATE_BUS_NUM: u8 = 0x1A;
-pub const OFFSET_TYPE1_SECONDARY_LATENCY_TIMER: u8 = 0x1B;
-pub const OFFSET_TYPE1_EXPANSION: u8 = 0x38;
+pub use address::PciAddress;
+
+pub const OFFSET_VENDOR_ID: u16 = 0x00;
+pub const OFFSET_DEVICE_ID: u16 = 0x02;
+pub const OFFSET_COMMAND: u16 = 0x04;
+pub const OFFSET_STATUS: u16 = 0x06;
+pub const OFFSET_REVISION_ID: u16 = 0x08;
+pub const OFFSET_PROG_INTF: u16 = 0x09;
+pub const OFFSET_SUB_CLASS: u16 = 0x0A;
+pub const OFFSET_BASE_CLASS: u16 = 0x0B;
+pub const OFFSET_CACHE_LINE_SIZE: u16 = 0x0C;
+pub const OFFSET_MASTER_LATENCY_TIMER: u16 = 0x0D;
+pub const OFFSET_HEADER_TYPE: u16 = 0x0E;
+pub const OFFSET_BIST: u16 = 0x0F;
+pub const OFFSET_CAPABILITIES_POINTER: u16 = 0x34;
+pub const OFFSET_INTERRUPT_LINE: u16 = 0x3C;
+pub const OFFSET_INTERRUPT_PIN: u16 = 0x3D;
+
+pub const OFFSET_TYPE0_BAR0: u16 = 0x10;
+pub const OFFSET_TYPE0_BAR1: u16 = 0x14;
+pub const OFFSET_TYPE0_BAR2: u16 = 0x18;
+pub const OFFSET_TYPE0_BAR3: u16 = 0x1C;
+pub const OFFSET_TYPE0_BAR4: u16 = 0x20;
+pub const OFFSET_TYPE0_BAR5: u16 = 0x24;
+pub const OFFSET_TYPE0_CARDBUS: u16 = 0x28;
+pub const OFFSET_TYPE0_SUBSYSTEM_VENDOR_ID: u16 = 0x2C;
+pub const OFFSET_TYPE0_SUBSYSTEM_ID: u16 = 0x2E;
+pub const OFFSET_TYPE0_EXPANSION: u16 = 0x30;
+
+pub const OFFSET_TYPE1_BAR0: u16 = 0x10;
+pub const OFFSET_TYPE1_BAR1: u16 = 0x14;
+pub const OFFSET_TYPE1_PRIMARY_BUS_NUM: u16 = 0x18;
+pub const OFFSET_TYPE1_SECONDARY_BUS_NUM: u16 = 0x19;
+pub const OFFSET_TYPE1_SUBORDINATE_BUS_NUM: u16 = 0x1A;
+pub const OFFSET_TYPE1_SECONDARY_LATENCY_TIMER: u16 = 0x1B;
+pub const OFFSET_TYPE1_IO_BASE: u16 = 0x1C;
+pub const OFFSET_TYPE1_IO_LIMIT: u16 = 0x1D;
+pub const OFFSET_TYPE1_SECONDARY_STATUS: u16 = 0x1E;
+pub const OFFSET_TYPE1_MEMORY_BASE: u16 = 0x20;
+pub const OFFSET_TYPE1_MEMORY_LIMIT: u16 = 0x22;
+pub const OFFSET_TYPE1_PREFETCHABLE_MEMORY_BASE: u16 = 0x24;
+pub const OFFSET_TYPE1_PREFETCHABLE_MEMORY_LIMIT: u16 = 0x26;
+pub const OFFSET_TYPE1_PREFETCHABLE_BASE_UPPER: u16 = 0x28;
+pub const OFFSET_TYPE1_PREFETCHABLE_LIMIT_UPPER: u16 = 0x2C;
+pub const OFFSET_TYPE1_IO_BASE_UPPER: u16 = 0x30;
+pub const OFFSET_TYPE1_IO_LIMIT_UPPER: u16 = 0x32;
+pub const OFFSET_TYPE1_EXPANSION: u16 = 0x38;
+pub const OFFSET_TYPE1_BRIDGE_CONTROL: u16 = 0x3E;
+
+pub const OFFSET_TYPE2_CARDBUS_SOCKET_BASE: u16 = 0x10;
+pub const OFFSET_TYPE2_CAPABILITIES_POINTER: u16 = 0x14;
+pub const OFFSET_TYPE2_SECONDARY_STATUS: u16 = 0x16;
+pub const OFFSET_TYPE2_PCI_BUS_NUM: u16 = 0x18;
+pub const OFFSET_TYPE2_CARDBUS_BUS_NUM: u16 = 0x19;
+pub const OFFSET_TYPE2_SUBORDINATE_BUS_NUM: u16 = 0x1A;
+pub const OFFSET_TYPE2_CARDBUS_LATENCY_TIMER: u16 = 0x1B;
+pub const OFFSET_TYPE2_MEMORY_BASE0: u16 = 0x1C;
+pub const OFFSET_TYPE2_MEMORY_LIMIT0: u16 = 0x20;
+pub const OFFSET_TYPE2_MEMORY_BASE1: u16 = 0x24;
+pub const OFFSET_TYPE2_MEMORY_LIMIT1: u16 = 0x28;
+pub const OFFSET_TYPE2_IO_BASE0: u16 = 0x2C;
+pub const OFFSET_TYPE2_IO_LIMIT0: u16 = 0x30;
+pub const OFFSET_TYPE2_IO_BASE1: u16 = 0x34;
+pub const OFFSET_TYPE2_IO_LIMIT1: u16 = 0x38;
+pub const OFFSET_TYPE2_BRIDGE_CONTROL: u16 = 0x3E;
+pub const OFFSET_TYPE2_SUBSYSTEM_VENDOR_ID: u16 = 0x40;
+pub const OFFSET_TYPE2_SUBSYSTEM_ID: u16 = 0x42;
+pub const OFFSET_TYPE2_LEGACY_BASE: u16 = 0x44;
 
 pub const OFFSET_BAR_TYPE_MASK: u32 = 0x01;
 pub const OFFSET_BAR_TYPE_IO: u32 = 0x01;
@@ -57,8 +111,16 @@ pub const OFFSET_BAR_ADDRSPACE_64BIT: u32 = 0x04;
 pub const OFFSET_BAR_PREFETCH_MASK: u32 = 0x08;
 pub const OFFSET_BAR_PREFETCH_ENABLE: u32 = 0x08;
 
+// Type 0x01 is a 32-bit I/O window or a 64-bit prefetchable memory window.
+pub const WINDOW_TYPE_MASK: u16 = 0x0F;
+pub const WINDOW_TYPE_32BIT: u16 = 0x01;
+pub const WINDOW_TYPE_64BIT: u16 = 0x01;
+
 pub const NOT_USED: u16 = 0xFFFF;
 
+pub const CONFIG_SPACE_SIZE: u16 = 0x100;
+pub const EXTENDED_CONFIG_SPACE_SIZE: u16 = 0x1000;
+
 #[derive(Debug)]
 #[repr(u8)]
 pub enum CapabilityId {
@@ -86,6 +148,64 @@ pub enum CapabilityId {
     FlatteningPortalBridge = 0x15,
 }
 
+#[derive(Debug)]
+#[repr(u16)]
+pub enum ExtendedCapabilityId {
+    Null = 0x0000,
+    Aer = 0x0001,
+    VirtualChannel = 0x0002,
+    DeviceSerialNumber = 0x0003,
+    PowerBudgeting = 0x0004,
+    RootComplexLinkDeclaration = 0x0005,
+    RootComplexInternalLinkControl = 0x0006,
+    RootComplexEventCollector = 0x0007,
+    MultiFunctionVirtualChannel = 0x0008,
+    VirtualChannelMfvc = 0x0009,
+    RcrbHeader = 0x000A,
+    VendorSpecific = 0x000B,
+    ConfigurationAccessCorrelation = 0x000C,
+    Acs = 0x000D,
+    Ari = 0x000E,
+    Ats = 0x000F,
+    SrIov = 0x0010,
+    MrIov = 0x0011,
+    Multicast = 0x0012,
+    PageRequest = 0x0013,
+    Amd = 0x0014,
+    ResizableBar = 0x0015,
+    DynamicPowerAllocation = 0x0016,
+    Tph = 0x0017,
+    Ltr = 0x0018,
+    SecondaryPciE = 0x0019,
+    Pmux = 0x001A,
+    Pasid = 0x001B,
+    Lnr = 0x001C,
+    Dpc = 0x001D,
+    L1PmSubstates = 0x001E,
+    Ptm = 0x001F,
+    MPciE = 0x0020,
+    FrsQueueing = 0x0021,
+    ReadinessTimeReporting = 0x0022,
+    Dvsec = 0x0023,
+    VfResizableBar = 0x0024,
+    DataLinkFeature = 0x0025,
+    PhysicalLayer16 = 0x0026,
+    LaneMargining = 0x0027,
+    HierarchyId = 0x0028,
+    Npem = 0x0029,
+    PhysicalLayer32 = 0x002A,
+    AlternateProtocol = 0x002B,
+    Sfi = 0x002C,
+    ShadowFunctions = 0x002D,
+    DataObjectExchange = 0x002E,
+    Device3 = 0x002F,
+    Ide = 0x0030,
+    PhysicalLayer64 = 0x0031,
+    FlitLogging = 0x0032,
+    FlitPerformanceMeasurement = 0x0033,
+    FlitErrorInjection = 0x0034,
+}
+
 pub fn get_pci_config<T: Method>(method: T) -> Option<PciConfig<T>> {
     let vendor_id = method.read16(OFFSET_VENDOR_ID);
     if vendor_id == NOT_USED {
@@ -97,6 +217,20 @@ pub fn get_pci_config<T: Method>(method: T) -> Option<PciConfig<T>> {
         return None;
     }
 
+    Some(read_pci_config(method, vendor_id, device_id))
+}
+
+// A virtual function reads all-ones for its IDs, so they come from the physical function
+// and the SR-IOV capability instead.
+pub fn get_vf_config<T: Method>(method: T, vendor_id: u16, device_id: u16) -> Option<PciConfig<T>> {
+    if method.read32(OFFSET_REVISION_ID) == 0xFFFF_FFFF {
+        return None;
+    }
+
+    Some(read_pci_config(method, vendor_id, device_id))
+}
+
+fn read_pci_config<T: Method>(method: T, vendor_id: u16, device_id: u16) -> PciConfig<T> {
     let command = method.read16(OFFSET_COMMAND);
     let status = method.read16(OFFSET_STATUS);
     let revision_id = method.read8(OFFSET_REVISION_ID);
@@ -107,11 +241,15 @@ pub fn get_pci_config<T: Method>(method: T) -> Option<PciConfig<T>> {
     let master_latency_timer = method.read8(OFFSET_MASTER_LATENCY_TIMER);
     let header_type = method.read8(OFFSET_HEADER_TYPE);
     let bist = method.read8(OFFSET_BIST);
-    let capabilities_pointer = method.read8(OFFSET_CAPABILITIES_POINTER);
+    let capabilities_pointer = if HeaderType(header_type).type2() {
+        method.read8(OFFSET_TYPE2_CAPABILITIES_POINTER)
+    } else {
+        method.read8(OFFSET_CAPABILITIES_POINTER)
+    };
     let interrupt_line = method.read8(OFFSET_INTERRUPT_LINE);
     let interrupt_pin = method.read8(OFFSET_INTERRUPT_PIN);
 
-    let config = PciConfig {
+    PciConfig {
         method: Rc::new(method),
         vendor_id,
         device_id,
@@ -126,9 +264,7 @@ pub fn get_pci_config<T: Method>(method: T) -> Option<PciConfig<T>> {
         capabilities_pointer,
         interrupt_line,
         interrupt_pin,
-    };
-
-    Some(config)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -202,6 +338,50 @@ impl<T: Method> PciConfig<T> {
         self.interrupt_pin
     }
 
+    pub fn config_size(&self) -> u16 {
+        self.method.config_size()
+    }
+
+    pub fn extended_config_space(&self) -> bool {
+        self.config_size() > CONFIG_SPACE_SIZE
+    }
+
+    pub fn set_command(&mut self, command: Command) -> Result<(), error::Error> {
+        self.method.write16(OFFSET_COMMAND, command.0)?;
+        self.command = command;
+        Ok(())
+    }
+
+    // The register may have changed since decoding, so the setters below start from the
+    // current value rather than the cached one.
+    fn read_command(&self) -> Command {
+        Command(self.method.read16(OFFSET_COMMAND))
+    }
+
+    pub fn set_io_space_enable(&mut self, value: bool) -> Result<(), error::Error> {
+        let mut command = self.read_command();
+        command.set_io_space_enable(value);
+        self.set_command(command)
+    }
+
+    pub fn set_memory_space_enable(&mut self, value: bool) -> Result<(), error::Error> {
+        let mut command = self.read_command();
+        command.set_memory_space_enable(value);
+        self.set_command(command)
+    }
+
+    pub fn set_bus_master_enable(&mut self, value: bool) -> Result<(), error::Error> {
+        let mut command = self.read_command();
+        command.set_bus_master_enable(value);
+        self.set_command(command)
+    }
+
+    pub fn set_interrupt_disable(&mut self, value: bool) -> Result<(), error::Error> {
+        let mut command = self.read_command();
+        command.set_interrupt_disable(value);
+        self.set_command(command)
+    }
+
     pub fn get_type0_header(&self) -> Option<PciConfigType0> {
         if !self.header_type().type0() {
             return None;
@@ -245,7 +425,19 @@ impl<T: Method> PciConfig<T> {
         let secondary_bus_number = self.method.read8(OFFSET_TYPE1_SECONDARY_BUS_NUM);
         let subordinate_bus_number = self.method.read8(OFFSET_TYPE1_SUBORDINATE_BUS_NUM);
         let secondary_latency_timer = self.method.read8(OFFSET_TYPE1_SECONDARY_LATENCY_TIMER);
+        let io_base = self.method.read8(OFFSET_TYPE1_IO_BASE);
+        let io_limit = self.method.read8(OFFSET_TYPE1_IO_LIMIT);
+        let secondary_status = self.method.read16(OFFSET_TYPE1_SECONDARY_STATUS);
+        let memory_base = self.method.read16(OFFSET_TYPE1_MEMORY_BASE);
+        let memory_limit = self.method.read16(OFFSET_TYPE1_MEMORY_LIMIT);
+        let prefetchable_memory_base = self.method.read16(OFFSET_TYPE1_PREFETCHABLE_MEMORY_BASE);
+        let prefetchable_memory_limit = self.method.read16(OFFSET_TYPE1_PREFETCHABLE_MEMORY_LIMIT);
+        let prefetchable_base_upper = self.method.read32(OFFSET_TYPE1_PREFETCHABLE_BASE_UPPER);
+        let prefetchable_limit_upper = self.method.read32(OFFSET_TYPE1_PREFETCHABLE_LIMIT_UPPER);
+        let io_base_upper = self.method.read16(OFFSET_TYPE1_IO_BASE_UPPER);
+        let io_limit_upper = self.method.read16(OFFSET_TYPE1_IO_LIMIT_UPPER);
         let expansion_rom = self.method.read32(OFFSET_TYPE1_EXPANSION);
+        let bridge_control = self.method.read16(OFFSET_TYPE1_BRIDGE_CONTROL);
 
         let t1 = PciConfigType1 {
             bar0,
@@ -254,15 +446,104 @@ impl<T: Method> PciConfig<T> {
             secondary_bus_number,
             subordinate_bus_number,
             secondary_latency_timer,
+            io_base,
+            io_limit,
+            secondary_status: SecondaryStatus(secondary_status),
+            memory_base,
+            memory_limit,
+            prefetchable_memory_base,
+            prefetchable_memory_limit,
+            prefetchable_base_upper,
+            prefetchable_limit_upper,
+            io_base_upper,
+            io_limit_upper,
             expansion_rom,
+            bridge_control: BridgeControl(bridge_control),
         };
 
         Some(t1)
     }
 
+    pub fn get_type2_header(&self) -> Option<PciConfigType2> {
+        if !self.header_type().type2() {
+            return None;
+        }
+
+        let cardbus_socket_base = self.method.read32(OFFSET_TYPE2_CARDBUS_SOCKET_BASE);
+        let secondary_status = self.method.read16(OFFSET_TYPE2_SECONDARY_STATUS);
+        let pci_bus_number = self.method.read8(OFFSET_TYPE2_PCI_BUS_NUM);
+        let cardbus_bus_number = self.method.read8(OFFSET_TYPE2_CARDBUS_BUS_NUM);
+        let subordinate_bus_number = self.method.read8(OFFSET_TYPE2_SUBORDINATE_BUS_NUM);
+        let cardbus_latency_timer = self.method.read8(OFFSET_TYPE2_CARDBUS_LATENCY_TIMER);
+        let memory_base0 = self.method.read32(OFFSET_TYPE2_MEMORY_BASE0);
+        let memory_limit0 = self.method.read32(OFFSET_TYPE2_MEMORY_LIMIT0);
+        let memory_base1 = self.method.read32(OFFSET_TYPE2_MEMORY_BASE1);
+        let memory_limit1 = self.method.read32(OFFSET_TYPE2_MEMORY_LIMIT1);
+        let io_base0 = self.method.read32(OFFSET_TYPE2_IO_BASE0);
+        let io_limit0 = self.method.read32(OFFSET_TYPE2_IO_LIMIT0);
+        let io_base1 = self.method.read32(OFFSET_TYPE2_IO_BASE1);
+        let io_limit1 = self.method.read32(OFFSET_TYPE2_IO_LIMIT1);
+        let bridge_control = self.method.read16(OFFSET_TYPE2_BRIDGE_CONTROL);
+        let subsystem_vendor_id = self.method.read16(OFFSET_TYPE2_SUBSYSTEM_VENDOR_ID);
+        let subsystem_id = self.method.read16(OFFSET_TYPE2_SUBSYSTEM_ID);
+        let legacy_base = self.method.read32(OFFSET_TYPE2_LEGACY_BASE);
+
+        let t2 = PciConfigType2 {
+            cardbus_socket_base,
+            secondary_status: SecondaryStatus(secondary_status),
+            pci_bus_number,
+            cardbus_bus_number,
+            subordinate_bus_number,
+            cardbus_latency_timer,
+            memory_base0,
+            memory_limit0,
+            memory_base1,
+            memory_limit1,
+            io_base0,
+            io_limit0,
+            io_base1,
+            io_limit1,
+            bridge_control: CardBusBridgeControl(bridge_control),
+            subsystem_vendor_id,
+            subsystem_id,
+            legacy_base,
+        };
+
+        Some(t2)
+    }
+
+    pub fn capabilities(&self) -> Capabilities<T> {
+        let next = if self.status.capabilities_list() {
+            self.capabilities_pointer
+        } else {
+            0
+        };
+
+        Capabilities::new(self.method.clone(), next)
+    }
+
+    pub fn extended_capabilities(&self) -> ExtendedCapabilities<T> {
+        let next = if self.extended_config_space() {
+            CONFIG_SPACE_SIZE
+        } else {
+            0
+        };
+
+        ExtendedCapabilities {
+            method: self.method.clone(),
+            next,
+            visited: BTreeSet::new(),
+            error: None,
+        }
+    }
+
+    pub fn rom_images(&self) -> Result<Vec<rom::RomImage>, error::Error> {
+        rom::parse(&self.method.read_rom()?)
+    }
+
     pub fn capability(&self) -> Option<PciCapability<T>> {
         let value = (self.capabilities_pointer as u32) << 8;
-        let cap = PciCapability::from(self.method.clone(), value);
+        let cap = PciCapability::from(self.method.clone(), 0, value);
         cap.next()
     }
 }
@@ -323,6 +604,14 @@ impl PciConfigType0 {
         self.expansion_rom
     }
 
+    // Looks up a BAR by register index, as used by MSI-X and Enhanced Allocation.
+    pub fn base_address(&self, index: u8) -> Option<PciBaseAddress> {
+        let bars = [
+            self.bar0, self.bar1, self.bar2, self.bar3, self.bar4, self.bar5,
+        ];
+        base_address(&bars, index)
+    }
+
     pub fn bars(&self) -> Vec<PciBaseAddress> {
         let mut addrs = vec![];
 
@@ -379,8 +668,19 @@ pub struct PciConfigType1 {
     secondary_bus_number: u8,
     subordinate_bus_number: u8,
     secondary_latency_timer: u8,
+    io_base: u8,
+    io_limit: u8,
+    secondary_status: SecondaryStatus,
+    memory_base: u16,
+    memory_limit: u16,
+    prefetchable_memory_base: u16,
+    prefetchable_memory_limit: u16,
+    prefetchable_base_upper: u32,
+    prefetchable_limit_upper: u32,
+    io_base_upper: u16,
+    io_limit_upper: u16,
     expansion_rom: u32,
-    // TODO:
+    bridge_control: BridgeControl,
 }
 
 impl PciConfigType1 {
@@ -392,7 +692,7 @@ impl PciConfigType1 {
         self.bar1
     }
 
-    pub fn parity_error_response(&self) -> u8 {
+    pub fn primary_bus_number(&self) -> u8 {
         self.primary_bus_number
     }
 
@@ -408,9 +708,271 @@ impl PciConfigType1 {
         self.secondary_latency_timer
     }
 
+    pub fn io_base(&self) -> u8 {
+        self.io_base
+    }
+
+    pub fn io_limit(&self) -> u8 {
+        self.io_limit
+    }
+
+    pub fn secondary_status(&self) -> SecondaryStatus {
+        self.secondary_status
+    }
+
+    pub fn memory_base(&self) -> u16 {
+        self.memory_base
+    }
+
+    pub fn memory_limit(&self) -> u16 {
+        self.memory_limit
+    }
+
+    pub fn prefetchable_memory_base(&self) -> u16 {
+        self.prefetchable_memory_base
+    }
+
+    pub fn prefetchable_memory_limit(&self) -> u16 {
+        self.prefetchable_memory_limit
+    }
+
+    pub fn prefetchable_base_upper(&self) -> u32 {
+        self.prefetchable_base_upper
+    }
+
+    pub fn prefetchable_limit_upper(&self) -> u32 {
+        self.prefetchable_limit_upper
+    }
+
+    pub fn io_base_upper(&self) -> u16 {
+        self.io_base_upper
+    }
+
+    pub fn io_limit_upper(&self) -> u16 {
+        self.io_limit_upper
+    }
+
     pub fn expansion_rom(&self) -> u32 {
         self.expansion_rom
     }
+
+    pub fn bridge_control(&self) -> BridgeControl {
+        self.bridge_control
+    }
+
+    pub fn io_32bit(&self) -> bool {
+        (self.io_base as u16 & WINDOW_TYPE_MASK) == WINDOW_TYPE_32BIT
+    }
+
+    pub fn prefetchable_64bit(&self) -> bool {
+        (self.prefetchable_memory_base & WINDOW_TYPE_MASK) == WINDOW_TYPE_64BIT
+    }
+
+    pub fn io_window(&self) -> BridgeWindow {
+        let mut base = ((self.io_base as u64) & 0xF0) << 8;
+        let mut limit = (((self.io_limit as u64) & 0xF0) << 8) | 0x0FFF;
+
+        if self.io_32bit() {
+            base |= (self.io_base_upper as u64) << 16;
+            limit |= (self.io_limit_upper as u64) << 16;
+        }
+
+        BridgeWindow { base, limit }
+    }
+
+    pub fn memory_window(&self) -> BridgeWindow {
+        let base = ((self.memory_base as u64) & 0xFFF0) << 16;
+        let limit = (((self.memory_limit as u64) & 0xFFF0) << 16) | 0x000F_FFFF;
+        BridgeWindow { base, limit }
+    }
+
+    pub fn prefetchable_memory_window(&self) -> BridgeWindow {
+        let mut base = ((self.prefetchable_memory_base as u64) & 0xFFF0) << 16;
+        let mut limit = (((self.prefetchable_memory_limit as u64) & 0xFFF0) << 16) | 0x000F_FFFF;
+
+        if self.prefetchable_64bit() {
+            base |= (self.prefetchable_base_upper as u64) << 32;
+            limit |= (self.prefetchable_limit_upper as u64) << 32;
+        }
+
+        BridgeWindow { base, limit }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PciConfigType2 {
+    cardbus_socket_base: u32,
+    secondary_status: SecondaryStatus,
+    pci_bus_number: u8,
+    cardbus_bus_number: u8,
+    subordinate_bus_number: u8,
+    cardbus_latency_timer: u8,
+    memory_base0: u32,
+    memory_limit0: u32,
+    memory_base1: u32,
+    memory_limit1: u32,
+    io_base0: u32,
+    io_limit0: u32,
+    io_base1: u32,
+    io_limit1: u32,
+    bridge_control: CardBusBridgeControl,
+    subsystem_vendor_id: u16,
+    subsystem_id: u16,
+    legacy_base: u32,
+}
+
+impl PciConfigType2 {
+    pub fn cardbus_socket_base(&self) -> u32 {
+        self.cardbus_socket_base
+    }
+
+    pub fn secondary_status(&self) -> SecondaryStatus {
+        self.secondary_status
+    }
+
+    pub fn pci_bus_number(&self) -> u8 {
+        self.pci_bus_number
+    }
+
+    pub fn cardbus_bus_number(&self) -> u8 {
+        self.cardbus_bus_number
+    }
+
+    pub fn subordinate_bus_number(&self) -> u8 {
+        self.subordinate_bus_number
+    }
+
+    pub fn cardbus_latency_timer(&self) -> u8 {
+        self.cardbus_latency_timer
+    }
+
+    pub fn memory_base0(&self) -> u32 {
+        self.memory_base0
+    }
+
+    pub fn memory_limit0(&self) -> u32 {
+        self.memory_limit0
+    }
+
+    pub fn memory_base1(&self) -> u32 {
+        self.memory_base1
+    }
+
+    pub fn memory_limit1(&self) -> u32 {
+        self.memory_limit1
+    }
+
+    pub fn io_base0(&self) -> u32 {
+        self.io_base0
+    }
+
+    pub fn io_limit0(&self) -> u32 {
+        self.io_limit0
+    }
+
+    pub fn io_base1(&self) -> u32 {
+        self.io_base1
+    }
+
+    pub fn io_limit1(&self) -> u32 {
+        self.io_limit1
+    }
+
+    pub fn bridge_control(&self) -> CardBusBridgeControl {
+        self.bridge_control
+    }
+
+    pub fn subsystem_vendor_id(&self) -> u16 {
+        self.subsystem_vendor_id
+    }
+
+    pub fn subsystem_id(&self) -> u16 {
+        self.subsystem_id
+    }
+
+    pub fn legacy_base(&self) -> u32 {
+        self.legacy_base
+    }
+
+    pub fn memory_window0(&self) -> BridgeWindow {
+        cardbus_memory_window(self.memory_base0, self.memory_limit0)
+    }
+
+    pub fn memory_window1(&self) -> BridgeWindow {
+        cardbus_memory_window(self.memory_base1, self.memory_limit1)
+    }
+
+    pub fn io_window0(&self) -> BridgeWindow {
+        cardbus_io_window(self.io_base0, self.io_limit0)
+    }
+
+    pub fn io_window1(&self) -> BridgeWindow {
+        cardbus_io_window(self.io_base1, self.io_limit1)
+    }
+}
+
+fn cardbus_memory_window(base: u32, limit: u32) -> BridgeWindow {
+    BridgeWindow {
+        base: (base & 0xFFFF_F000) as u64,
+        limit: (limit as u64 & 0xFFFF_F000) | 0x0FFF,
+    }
+}
+
+fn cardbus_io_window(base: u32, limit: u32) -> BridgeWindow {
+    let (base, limit) = if (base & 0x01) == 0x01 {
+        (base, limit)
+    } else {
+        (base & 0xFFFF, limit & 0xFFFF)
+    };
+
+    BridgeWindow {
+        base: (base & 0xFFFF_FFFC) as u64,
+        limit: (limit as u64 & 0xFFFF_FFFC) | 0x03,
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BridgeWindow {
+    base: u64,
+    limit: u64,
+}
+
+impl BridgeWindow {
+    pub fn base(&self) -> u64 {
+        self.base
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    // A bridge disables a window by programming the base above the limit.
+    pub fn enabled(&self) -> bool {
+        self.base <= self.limit
+    }
+
+    pub fn size(&self) -> u64 {
+        if self.enabled() {
+            self.limit - self.base + 1
+        } else {
+            0
+        }
+    }
+}
+
+// The upper half of a 64-bit BAR has no address of its own.
+pub(crate) fn base_address(bars: &[u32], index: u8) -> Option<PciBaseAddress> {
+    let mut i = 0;
+    while i < bars.len() {
+        let addr = PciBaseAddress::from(bars[i], bars.get(i + 1).copied().unwrap_or(0));
+        if i == index as usize {
+            return Some(addr);
+        }
+
+        i += if addr.b64() { 2 } else { 1 };
+    }
+
+    None
 }
 
 #[derive(Clone, Debug, Default)]
@@ -473,14 +1035,16 @@ impl PciBaseAddress {
 #[derive(Clone, Debug)]
 pub struct PciCapability<T: Method> {
     method: Rc<T>,
+    offset: u8,
     id: u8,
     next_pointer: u8,
 }
 
 impl<T: Method> PciCapability<T> {
-    pub fn from(method: Rc<T>, value: u32) -> Self {
+    pub fn from(method: Rc<T>, offset: u8, value: u32) -> Self {
         PciCapability {
             method,
+            offset,
             id: (value & 0x0000_00FF) as u8,
             next_pointer: ((value & 0x0000_FF00) >> 8) as u8,
         }
@@ -514,17 +1078,272 @@ impl<T: Method> PciCapability<T> {
         }
     }
 
+    pub fn offset(&self) -> u8 {
+        self.offset
+    }
+
     pub fn next_pointer(&self) -> u8 {
         self.next_pointer
     }
 
+    pub fn decode(&self) -> capability::Capability<T> {
+        capability::Capability::decode(self)
+    }
+
     pub fn next(&self) -> Option<PciCapability<T>> {
         if self.next_pointer == 0 {
             None
         } else {
-            let data = self.method.read32(self.next_pointer);
-            Some(PciCapability::from(self.method.clone(), data))
+            let next_pointer = self.next_pointer & !0x03;
+            let data = self.method.read32(next_pointer as u16);
+            Some(PciCapability::from(self.method.clone(), next_pointer, data))
+        }
+    }
+}
+
+pub struct Capabilities<T: Method> {
+    method: Rc<T>,
+    next: u8,
+    visited: BTreeSet<u8>,
+    error: Option<error::Error>,
+}
+
+impl<T: Method> Capabilities<T> {
+    fn new(method: Rc<T>, pointer: u8) -> Self {
+        Capabilities {
+            method,
+            next: pointer & !0x03,
+            visited: BTreeSet::new(),
+            error: None,
+        }
+    }
+
+    pub fn error(&self) -> Option<&error::Error> {
+        self.error.as_ref()
+    }
+}
+
+impl<T: Method> Iterator for Capabilities<T> {
+    type Item = PciCapability<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.next;
+        if offset == 0 {
+            return None;
+        }
+        self.next = 0;
+
+        // The first 64 bytes are the header itself.
+        if offset < 0x40 {
+            self.error = Some(error::Error::InvalidCapabilityPointer(offset as u16));
+            return None;
+        }
+
+        if !self.visited.insert(offset) {
+            self.error = Some(error::Error::CapabilityLoop(offset as u16));
+            return None;
+        }
+
+        // A function that has gone away reads all-ones.
+        let value = self.method.read32(offset as u16);
+        if value == 0xFFFF_FFFF {
+            self.error = Some(error::Error::InvalidCapabilityPointer(offset as u16));
+            return None;
         }
+
+        let cap = PciCapability::from(self.method.clone(), offset, value);
+        self.next = cap.next_pointer & !0x03;
+        Some(cap)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PciExtendedCapability<T: Method> {
+    method: Rc<T>,
+    offset: u16,
+    id: u16,
+    version: u8,
+    next_pointer: u16,
+}
+
+impl<T: Method> PciExtendedCapability<T> {
+    pub fn from(method: Rc<T>, offset: u16, value: u32) -> Self {
+        PciExtendedCapability {
+            method,
+            offset,
+            id: (value & 0x0000_FFFF) as u16,
+            version: ((value & 0x000F_0000) >> 16) as u8,
+            next_pointer: ((value & 0xFFF0_0000) >> 20) as u16,
+        }
+    }
+
+    pub fn id(&self) -> Option<ExtendedCapabilityId> {
+        match self.id {
+            0x0000 => Some(ExtendedCapabilityId::Null),
+            0x0001 => Some(ExtendedCapabilityId::Aer),
+            0x0002 => Some(ExtendedCapabilityId::VirtualChannel),
+            0x0003 => Some(ExtendedCapabilityId::DeviceSerialNumber),
+            0x0004 => Some(ExtendedCapabilityId::PowerBudgeting),
+            0x0005 => Some(ExtendedCapabilityId::RootComplexLinkDeclaration),
+            0x0006 => Some(ExtendedCapabilityId::RootComplexInternalLinkControl),
+            0x0007 => Some(ExtendedCapabilityId::RootComplexEventCollector),
+            0x0008 => Some(ExtendedCapabilityId::MultiFunctionVirtualChannel),
+            0x0009 => Some(ExtendedCapabilityId::VirtualChannelMfvc),
+            0x000A => Some(ExtendedCapabilityId::RcrbHeader),
+            0x000B => Some(ExtendedCapabilityId::VendorSpecific),
+            0x000C => Some(ExtendedCapabilityId::ConfigurationAccessCorrelation),
+            0x000D => Some(ExtendedCapabilityId::Acs),
+            0x000E => Some(ExtendedCapabilityId::Ari),
+            0x000F => Some(ExtendedCapabilityId::Ats),
+            0x0010 => Some(ExtendedCapabilityId::SrIov),
+            0x0011 => Some(ExtendedCapabilityId::MrIov),
+            0x0012 => Some(ExtendedCapabilityId::Multicast),
+            0x0013 => Some(ExtendedCapabilityId::PageRequest),
+            0x0014 => Some(ExtendedCapabilityId::Amd),
+            0x0015 => Some(ExtendedCapabilityId::ResizableBar),
+            0x0016 => Some(ExtendedCapabilityId::DynamicPowerAllocation),
+            0x0017 => Some(ExtendedCapabilityId::Tph),
+            0x0018 => Some(ExtendedCapabilityId::Ltr),
+            0x0019 => Some(ExtendedCapabilityId::SecondaryPciE),
+            0x001A => Some(ExtendedCapabilityId::Pmux),
+            0x001B => Some(ExtendedCapabilityId::Pasid),
+            0x001C => Some(ExtendedCapabilityId::Lnr),
+            0x001D => Some(ExtendedCapabilityId::Dpc),
+            0x001E => Some(ExtendedCapabilityId::L1PmSubstates),
+            0x001F => Some(ExtendedCapabilityId::Ptm),
+            0x0020 => Some(ExtendedCapabilityId::MPciE),
+            0x0021 => Some(ExtendedCapabilityId::FrsQueueing),
+            0x0022 => Some(ExtendedCapabilityId::ReadinessTimeReporting),
+            0x0023 => Some(ExtendedCapabilityId::Dvsec),
+            0x0024 => Some(ExtendedCapabilityId::VfResizableBar),
+            0x0025 => Some(ExtendedCapabilityId::DataLinkFeature),
+            0x0026 => Some(ExtendedCapabilityId::PhysicalLayer16),
+            0x0027 => Some(ExtendedCapabilityId::LaneMargining),
+            0x0028 => Some(ExtendedCapabilityId::HierarchyId),
+            0x0029 => Some(ExtendedCapabilityId::Npem),
+            0x002A => Some(ExtendedCapabilityId::PhysicalLayer32),
+            0x002B => Some(ExtendedCapabilityId::AlternateProtocol),
+            0x002C => Some(ExtendedCapabilityId::Sfi),
+            0x002D => Some(ExtendedCapabilityId::ShadowFunctions),
+            0x002E => Some(ExtendedCapabilityId::DataObjectExchange),
+            0x002F => Some(ExtendedCapabilityId::Device3),
+            0x0030 => Some(ExtendedCapabilityId::Ide),
+            0x0031 => Some(ExtendedCapabilityId::PhysicalLayer64),
+            0x0032 => Some(ExtendedCapabilityId::FlitLogging),
+            0x0033 => Some(ExtendedCapabilityId::FlitPerformanceMeasurement),
+            0x0034 => Some(ExtendedCapabilityId::FlitErrorInjection),
+            _ => None,
+        }
+    }
+
+    pub fn raw_id(&self) -> u16 {
+        self.id
+    }
+
+    pub fn decode(&self) -> capability::ExtendedCapability<T> {
+        capability::ExtendedCapability::decode(self)
+    }
+
+    // Only PCI Express functions have extended capabilities, so their PCI Express
+    // capability tells what kind of port owns this one.
+    pub(crate) fn port_type(&self) -> Option<pcie::DevicePortType> {
+        let pointer = if Status(self.method.read16(OFFSET_STATUS)).capabilities_list() {
+            self.method.read8(OFFSET_CAPABILITIES_POINTER)
+        } else {
+            0
+        };
+
+        Capabilities::new(self.method.clone(), pointer).find_map(|c| match c.decode() {
+            capability::Capability::PciE(pcie) => Some(pcie.device_port_type()),
+            _ => None,
+        })
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn offset(&self) -> u16 {
+        self.offset
+    }
+
+    pub fn next_pointer(&self) -> u16 {
+        self.next_pointer
+    }
+
+    pub fn read8(&self, register: u16) -> u8 {
+        self.method.read8(self.offset + register)
+    }
+
+    pub fn read16(&self, register: u16) -> u16 {
+        self.method.read16(self.offset + register)
+    }
+
+    pub fn read32(&self, register: u16) -> u32 {
+        self.method.read32(self.offset + register)
+    }
+
+    pub fn write8(&self, register: u16, value: u8) -> Result<(), error::Error> {
+        self.method.write8(self.offset + register, value)
+    }
+
+    pub fn write16(&self, register: u16, value: u16) -> Result<(), error::Error> {
+        self.method.write16(self.offset + register, value)
+    }
+
+    pub fn write32(&self, register: u16, value: u32) -> Result<(), error::Error> {
+        self.method.write32(self.offset + register, value)
+    }
+}
+
+pub struct ExtendedCapabilities<T: Method> {
+    method: Rc<T>,
+    next: u16,
+    visited: BTreeSet<u16>,
+    error: Option<error::Error>,
+}
+
+impl<T: Method> ExtendedCapabilities<T> {
+    // A walk that stopped on a malformed list leaves the reason here.
+    pub fn error(&self) -> Option<&error::Error> {
+        self.error.as_ref()
+    }
+}
+
+impl<T: Method> Iterator for ExtendedCapabilities<T> {
+    type Item = PciExtendedCapability<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.next;
+        if offset == 0 {
+            return None;
+        }
+        self.next = 0;
+
+        if offset < CONFIG_SPACE_SIZE {
+            self.error = Some(error::Error::InvalidCapabilityPointer(offset));
+            return None;
+        }
+
+        if !self.visited.insert(offset) {
+            self.error = Some(error::Error::CapabilityLoop(offset));
+            return None;
+        }
+
+        // A zero header only means an empty list at the start of the extended space.
+        let value = self.method.read32(offset);
+        if value == 0 && offset == CONFIG_SPACE_SIZE {
+            return None;
+        }
+
+        if value == 0 || value == 0xFFFF_FFFF {
+            self.error = Some(error::Error::InvalidCapabilityPointer(offset));
+            return None;
+        }
+
+        let cap = PciExtendedCapability::from(self.method.clone(), offset, value);
+        self.next = cap.next_pointer & !0x03;
+        Some(cap)
     }
 }
 
@@ -576,10 +1395,63 @@ impl Command {
         self.get_bool(10)
     }
 
+    pub fn set_io_space_enable(&mut self, value: bool) {
+        self.set_bool(0, value)
+    }
+
+    pub fn set_memory_space_enable(&mut self, value: bool) {
+        self.set_bool(1, value)
+    }
+
+    pub fn set_bus_master_enable(&mut self, value: bool) {
+        self.set_bool(2, value)
+    }
+
+    pub fn set_special_cycle_enable(&mut self, value: bool) {
+        self.set_bool(3, value)
+    }
+
+    pub fn set_memory_write_and_invalidate(&mut self, value: bool) {
+        self.set_bool(4, value)
+    }
+
+    pub fn set_vga_palette_snoop(&mut self, value: bool) {
+        self.set_bool(5, value)
+    }
+
+    pub fn set_parity_error_response(&mut self, value: bool) {
+        self.set_bool(6, value)
+    }
+
+    pub fn set_idsel_stepping_wait_cycle_control(&mut self, value: bool) {
+        self.set_bool(7, value)
+    }
+
+    pub fn set_serr_enable(&mut self, value: bool) {
+        self.set_bool(8, value)
+    }
+
+    pub fn set_fast_back_to_back_transactions_enable(&mut self, value: bool) {
+        self.set_bool(9, value)
+    }
+
+    pub fn set_interrupt_disable(&mut self, value: bool) {
+        self.set_bool(10, value)
+    }
+
     fn get_bool(&self, bit: u8) -> bool {
         let mask: u16 = 1 << bit;
         (self.0 & mask) == mask
     }
+
+    fn set_bool(&mut self, bit: u8, value: bool) {
+        let mask: u16 = 1 << bit;
+        if value {
+            self.0 |= mask;
+        } else {
+            self.0 &= !mask;
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -640,6 +1512,160 @@ impl Status {
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+pub struct SecondaryStatus(u16);
+
+impl SecondaryStatus {
+    pub fn mhz_66_capable(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn fast_back_to_back_transactions_capable(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    pub fn master_data_parity_error(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    pub fn devsel_timing(&self) -> u8 {
+        ((self.0 >> 9) & 0x0003) as u8
+    }
+
+    pub fn signaled_target_abort(&self) -> bool {
+        self.get_bool(11)
+    }
+
+    pub fn received_target_abort(&self) -> bool {
+        self.get_bool(12)
+    }
+
+    pub fn received_master_abort(&self) -> bool {
+        self.get_bool(13)
+    }
+
+    pub fn received_system_error(&self) -> bool {
+        self.get_bool(14)
+    }
+
+    pub fn detected_parity_error(&self) -> bool {
+        self.get_bool(15)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct BridgeControl(u16);
+
+impl BridgeControl {
+    pub fn parity_error_response_enable(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn serr_enable(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn isa_enable(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn vga_enable(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn vga_16bit_decode(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn master_abort_mode(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn secondary_bus_reset(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn fast_back_to_back_transactions_enable(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    pub fn primary_discard_timeout(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    pub fn secondary_discard_timeout(&self) -> bool {
+        self.get_bool(9)
+    }
+
+    pub fn discard_timer_status(&self) -> bool {
+        self.get_bool(10)
+    }
+
+    pub fn discard_timer_serr_enable(&self) -> bool {
+        self.get_bool(11)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CardBusBridgeControl(u16);
+
+impl CardBusBridgeControl {
+    pub fn parity_error_response_enable(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn serr_enable(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn isa_enable(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn vga_enable(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn master_abort_mode(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn cardbus_reset(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn interrupt_enable_16bit(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    pub fn memory0_prefetch_enable(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    pub fn memory1_prefetch_enable(&self) -> bool {
+        self.get_bool(9)
+    }
+
+    pub fn write_posting_enable(&self) -> bool {
+        self.get_bool(10)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct ClassCode(u8, u8, u8);
 
@@ -669,6 +1695,19 @@ impl HeaderType {
         self.get_type() == 1
     }
 
+    pub fn type2(&self) -> bool {
+        self.get_type() == 2
+    }
+
+    pub fn layout(&self) -> HeaderLayout {
+        match self.get_type() {
+            0 => HeaderLayout::Type0,
+            1 => HeaderLayout::Type1,
+            2 => HeaderLayout::Type2,
+            v => HeaderLayout::Unknown(v),
+        }
+    }
+
     pub fn multi_function_device(&self) -> bool {
         self.get_bool(7)
     }
@@ -683,22 +1722,348 @@ impl HeaderType {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderLayout {
+    Type0,
+    Type1,
+    Type2,
+    Unknown(u8),
+}
+
 pub trait Method: Sized + Clone {
-    fn try_from(bus: u8, device: u8, func: u8) -> Result<Self, error::Error>;
+    fn try_from(address: PciAddress) -> Result<Self, error::Error>;
+
+    fn config_size(&self) -> u16;
+
+    // Reads past the end of the configuration space return all-ones, like an absent function.
+    fn read8(&self, offset: u16) -> u8;
 
-    fn read8(&self, offset: u8) -> u8;
+    fn read16(&self, offset: u16) -> u16;
 
-    fn read16(&self, offset: u8) -> u16;
+    fn read32(&self, offset: u16) -> u32;
 
-    fn read32(&self, offset: u8) -> u32;
+    fn write8(&self, offset: u16, value: u8) -> Result<(), error::Error>;
+
+    fn write16(&self, offset: u16, value: u16) -> Result<(), error::Error>;
+
+    fn write32(&self, offset: u16, value: u32) -> Result<(), error::Error>;
+
+    // Contents of the expansion ROM, for the backends that have a way to reach it.
+    fn read_rom(&self) -> Result<Vec<u8>, error::Error> {
+        Err(error::Error::UnsupportedRomAccess)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::memory::Memory;
     use super::*;
+    use capability::Capability;
     use std::fs::File;
     use std::io::prelude::*;
 
+    fn type0_config() -> Vec<u8> {
+        let mut data = vec![0u8; 256];
+        data[0x00..0x04].copy_from_slice(&[0x86, 0x80, 0x0E, 0x10]);
+        data[0x04..0x08].copy_from_slice(&[0x07, 0x00, 0x10, 0x00]);
+        data[0x08..0x0C].copy_from_slice(&[0x02, 0x00, 0x00, 0x02]);
+        data[0x10..0x14].copy_from_slice(&0xFEB8_0000u32.to_le_bytes());
+        data[0x14..0x18].copy_from_slice(&0x0000_C001u32.to_le_bytes());
+        data[0x18..0x1C].copy_from_slice(&0xFE00_000Cu32.to_le_bytes());
+        data[0x1C..0x20].copy_from_slice(&0x0000_0001u32.to_le_bytes());
+        data[0x2C..0x30].copy_from_slice(&[0x86, 0x80, 0x1E, 0x00]);
+        data[0x34] = 0x40;
+        data[0x40..0x44].copy_from_slice(&[0x01, 0x50, 0x03, 0x00]);
+        data[0x44..0x46].copy_from_slice(&0x0008u16.to_le_bytes());
+        data[0x50..0x54].copy_from_slice(&[0x05, 0x60, 0x80, 0x00]);
+        data[0x60..0x62].copy_from_slice(&[0xEE, 0x00]);
+        data
+    }
+
+    #[test]
+    fn decode_type0() {
+        let method = Memory::new(&type0_config()).unwrap();
+        let cfg = get_pci_config(method).unwrap();
+        assert_eq!(cfg.vendor_id(), 0x8086);
+        assert_eq!(cfg.device_id(), 0x100E);
+        assert!(cfg.command().bus_master_enable());
+        assert!(cfg.status().capabilities_list());
+        assert_eq!(cfg.class_code().base_class(), 0x02);
+        assert_eq!(cfg.revision_id(), 0x02);
+
+        let t0 = cfg.get_type0_header().unwrap();
+        assert_eq!(t0.subsystem_vendor_id(), 0x8086);
+        assert_eq!(t0.subsystem_id(), 0x001E);
+
+        let bars = t0.bars();
+        assert_eq!(bars.len(), 5);
+        assert_eq!(bars[0].bar(), 0xFEB8_0000);
+        assert!(bars[1].io_space());
+        assert_eq!(bars[1].bar(), 0xC000);
+        assert!(bars[2].b64());
+        assert!(bars[2].prefetchable());
+        assert_eq!(bars[2].bar(), 0x0000_0001_FE00_0000);
+
+        let cap = cfg.capability().unwrap();
+        assert!(matches!(cap.id(), Some(CapabilityId::Pm)));
+        assert_eq!(cap.offset(), 0x40);
+        match cap.decode() {
+            Capability::Pm(pm) => {
+                assert_eq!(pm.version(), 3);
+                assert!(pm.control_status().no_soft_reset());
+            }
+            _ => panic!("expected a power management capability"),
+        }
+
+        let cap = cap.next().unwrap();
+        assert!(matches!(cap.id(), Some(CapabilityId::Msi)));
+        match cap.decode() {
+            Capability::Msi(msi) => assert!(msi.message_control().is_64bit()),
+            _ => panic!("expected an MSI capability"),
+        }
+
+        let cap = cap.next().unwrap();
+        assert!(cap.id().is_none());
+        let Capability::Unknown(raw) = cap.decode() else {
+            panic!("expected an unknown capability");
+        };
+        assert_eq!(raw.offset(), 0x60);
+        assert_eq!(raw.id(), 0xEE);
+        raw.write16(0x02, 0x1234).unwrap();
+        assert_eq!(raw.read16(0x02), 0x1234);
+        assert!(cap.next().is_none());
+    }
+
+    #[test]
+    fn capability_chain() {
+        let mut data = type0_config();
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        let offsets: Vec<u8> = cfg.capabilities().map(|c| c.offset()).collect();
+        assert_eq!(offsets, vec![0x40, 0x50, 0x60]);
+
+        // A self-loop with the reserved low bits set.
+        data[0x61] = 0x63;
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        let mut caps = cfg.capabilities();
+        assert_eq!(caps.by_ref().count(), 3);
+        assert!(matches!(
+            caps.error(),
+            Some(error::Error::CapabilityLoop(0x60))
+        ));
+
+        data[0x61] = 0x20;
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        let mut caps = cfg.capabilities();
+        assert_eq!(caps.by_ref().count(), 3);
+        assert!(matches!(
+            caps.error(),
+            Some(error::Error::InvalidCapabilityPointer(0x20))
+        ));
+
+        data[0x61] = 0x70;
+        data[0x70..0x74].copy_from_slice(&[0xFF; 4]);
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        let mut caps = cfg.capabilities();
+        assert_eq!(caps.by_ref().count(), 3);
+        assert!(matches!(
+            caps.error(),
+            Some(error::Error::InvalidCapabilityPointer(0x70))
+        ));
+
+        data[0x06] = 0x00;
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        assert_eq!(cfg.capabilities().count(), 0);
+    }
+
+    #[test]
+    fn extended_capabilities() {
+        let mut data = type0_config();
+        data.resize(EXTENDED_CONFIG_SPACE_SIZE as usize, 0);
+        data[0x100..0x104].copy_from_slice(&0x1402_0001u32.to_le_bytes());
+        data[0x140..0x144].copy_from_slice(&0x1B01_0003u32.to_le_bytes());
+        data[0x1B0..0x1B4].copy_from_slice(&0x0001_ABCDu32.to_le_bytes());
+
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        let mut caps = cfg.extended_capabilities();
+        let aer = caps.next().unwrap();
+        assert!(matches!(aer.id(), Some(ExtendedCapabilityId::Aer)));
+        assert_eq!(aer.version(), 2);
+        assert_eq!(aer.offset(), 0x100);
+        let dsn = caps.next().unwrap();
+        assert!(matches!(
+            dsn.id(),
+            Some(ExtendedCapabilityId::DeviceSerialNumber)
+        ));
+        assert_eq!(dsn.next_pointer(), 0x1B0);
+        let unknown = caps.next().unwrap();
+        assert!(unknown.id().is_none());
+        assert_eq!(unknown.raw_id(), 0xABCD);
+        assert!(caps.next().is_none());
+        assert!(caps.error().is_none());
+
+        // Point the last entry back at the first one.
+        data[0x1B0..0x1B4].copy_from_slice(&0x1001_ABCDu32.to_le_bytes());
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        let mut caps = cfg.extended_capabilities();
+        assert_eq!(caps.by_ref().count(), 3);
+        assert!(matches!(
+            caps.error(),
+            Some(error::Error::CapabilityLoop(0x100))
+        ));
+
+        // A next pointer to a zero header.
+        data[0x1B0..0x1B4].copy_from_slice(&0x1C01_ABCDu32.to_le_bytes());
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        let mut caps = cfg.extended_capabilities();
+        assert_eq!(caps.by_ref().count(), 3);
+        assert!(matches!(
+            caps.error(),
+            Some(error::Error::InvalidCapabilityPointer(0x1C0))
+        ));
+
+        data[0x100..0x104].copy_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        let mut caps = cfg.extended_capabilities();
+        assert_eq!(caps.by_ref().count(), 0);
+        assert!(matches!(
+            caps.error(),
+            Some(error::Error::InvalidCapabilityPointer(0x100))
+        ));
+
+        data[0x100..0x104].copy_from_slice(&[0; 4]);
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        let mut caps = cfg.extended_capabilities();
+        assert_eq!(caps.by_ref().count(), 0);
+        assert!(caps.error().is_none());
+
+        let cfg = get_pci_config(Memory::new(&type0_config()).unwrap()).unwrap();
+        assert_eq!(cfg.extended_capabilities().count(), 0);
+    }
+
+    #[test]
+    fn decode_type1() {
+        let mut data = vec![0u8; 64];
+        data[0x00..0x04].copy_from_slice(&[0x86, 0x80, 0x10, 0x34]);
+        data[0x0E] = 0x01;
+        data[0x18..0x1C].copy_from_slice(&[0x00, 0x02, 0x05, 0x00]);
+        data[0x1C..0x1E].copy_from_slice(&[0x31, 0x41]);
+        data[0x1E..0x20].copy_from_slice(&0x2000u16.to_le_bytes());
+        data[0x20..0x24].copy_from_slice(&[0x00, 0xFE, 0x10, 0xFE]);
+        data[0x24..0x28].copy_from_slice(&[0xF1, 0xFF, 0x01, 0x00]);
+        data[0x28..0x2C].copy_from_slice(&0x0000_0001u32.to_le_bytes());
+        data[0x2C..0x30].copy_from_slice(&0x0000_0000u32.to_le_bytes());
+        data[0x30..0x34].copy_from_slice(&[0x01, 0x00, 0x01, 0x00]);
+        data[0x3E..0x40].copy_from_slice(&0x0003u16.to_le_bytes());
+
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        let t1 = cfg.get_type1_header().unwrap();
+        assert_eq!(t1.primary_bus_number(), 0x00);
+        assert_eq!(t1.secondary_bus_number(), 0x02);
+        assert_eq!(t1.subordinate_bus_number(), 0x05);
+        assert!(t1.secondary_status().received_master_abort());
+        assert!(t1.bridge_control().parity_error_response_enable());
+        assert!(t1.bridge_control().serr_enable());
+
+        let io = t1.io_window();
+        assert!(t1.io_32bit());
+        assert_eq!(io.base(), 0x0001_3000);
+        assert_eq!(io.limit(), 0x0001_4FFF);
+
+        let mem = t1.memory_window();
+        assert_eq!(mem.base(), 0xFE00_0000);
+        assert_eq!(mem.limit(), 0xFE1F_FFFF);
+        assert_eq!(mem.size(), 0x0020_0000);
+
+        let pref = t1.prefetchable_memory_window();
+        assert!(t1.prefetchable_64bit());
+        assert!(!pref.enabled());
+        assert_eq!(pref.size(), 0);
+    }
+
+    #[test]
+    fn decode_type2() {
+        let mut data = vec![0u8; 0x48];
+        data[0x00..0x04].copy_from_slice(&[0x80, 0x10, 0x76, 0x04]);
+        data[0x06] = 0x10;
+        data[0x0E] = 0x82;
+        data[0x10..0x14].copy_from_slice(&0xFEB0_0000u32.to_le_bytes());
+        data[0x14] = 0x80;
+        data[0x18..0x1C].copy_from_slice(&[0x00, 0x03, 0x06, 0xB0]);
+        data[0x1C..0x20].copy_from_slice(&0xF000_0000u32.to_le_bytes());
+        data[0x20..0x24].copy_from_slice(&0xF03F_F000u32.to_le_bytes());
+        data[0x24..0x28].copy_from_slice(&0xFFFF_F000u32.to_le_bytes());
+        data[0x2C..0x30].copy_from_slice(&0xFFFF_4000u32.to_le_bytes());
+        data[0x30..0x34].copy_from_slice(&0x0000_40FCu32.to_le_bytes());
+        data[0x3E..0x40].copy_from_slice(&0x0300u16.to_le_bytes());
+        data[0x40..0x44].copy_from_slice(&[0x28, 0x10, 0x12, 0x00]);
+
+        let cfg = get_pci_config(Memory::new(&data).unwrap()).unwrap();
+        assert_eq!(cfg.header_type().layout(), HeaderLayout::Type2);
+        assert!(cfg.header_type().multi_function_device());
+        assert_eq!(cfg.capabilities_pointer(), 0x80);
+        assert!(cfg.get_type0_header().is_none());
+        assert!(cfg.get_type1_header().is_none());
+
+        let t2 = cfg.get_type2_header().unwrap();
+        assert_eq!(t2.cardbus_socket_base(), 0xFEB0_0000);
+        assert_eq!(t2.cardbus_bus_number(), 0x03);
+        assert_eq!(t2.subordinate_bus_number(), 0x06);
+        assert_eq!(t2.cardbus_latency_timer(), 0xB0);
+        assert_eq!(t2.subsystem_vendor_id(), 0x1028);
+        assert_eq!(t2.subsystem_id(), 0x0012);
+        assert!(t2.bridge_control().memory0_prefetch_enable());
+        assert!(t2.bridge_control().memory1_prefetch_enable());
+
+        let mem = t2.memory_window0();
+        assert_eq!(mem.base(), 0xF000_0000);
+        assert_eq!(mem.limit(), 0xF03F_FFFF);
+        assert!(!t2.memory_window1().enabled());
+
+        let io = t2.io_window0();
+        assert_eq!(io.base(), 0x4000);
+        assert_eq!(io.limit(), 0x40FF);
+
+        let cfg = get_pci_config(Memory::new(&type0_config()).unwrap()).unwrap();
+        assert!(cfg.get_type2_header().is_none());
+
+        assert_eq!(HeaderType(0x7F).layout(), HeaderLayout::Unknown(0x7F));
+    }
+
+    #[test]
+    fn write_command() {
+        let method = Memory::new(&type0_config()).unwrap();
+        let mut cfg = get_pci_config(method.clone()).unwrap();
+        cfg.set_bus_master_enable(false).unwrap();
+        assert!(!cfg.command().bus_master_enable());
+        assert_eq!(method.read16(OFFSET_COMMAND), 0x0003);
+    }
+
+    #[test]
+    fn command_setters() {
+        let method = Memory::new(&type0_config()).unwrap();
+        let mut cfg = get_pci_config(method.clone()).unwrap();
+
+        cfg.set_io_space_enable(false).unwrap();
+        assert_eq!(method.read16(OFFSET_COMMAND), 0x0006);
+        cfg.set_memory_space_enable(false).unwrap();
+        assert_eq!(method.read16(OFFSET_COMMAND), 0x0004);
+        cfg.set_interrupt_disable(true).unwrap();
+        assert_eq!(method.read16(OFFSET_COMMAND), 0x0404);
+        assert!(cfg.command().interrupt_disable());
+
+        // A change made through another handle since decoding is kept.
+        method.write16(OFFSET_COMMAND, 0x0001).unwrap();
+        cfg.set_bus_master_enable(true).unwrap();
+        assert_eq!(method.read16(OFFSET_COMMAND), 0x0005);
+        assert!(cfg.command().io_space_enable());
+    }
+
+    #[test]
+    fn absent_function() {
+        let method = <Memory as Method>::try_from(PciAddress::default()).unwrap();
+        assert!(get_pci_config(method).is_none());
+    }
+
     #[test]
     fn parse() {
         let mut f = File::open("src/pciids/pci.ids").unwrap();
@@ -0,0 +1,139 @@
+use super::error;
+use super::{CONFIG_SPACE_SIZE, EXTENDED_CONFIG_SPACE_SIZE, Method, PciAddress};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+// A single function backed by a buffer. `enumerate::<Memory>()` finds nothing; pass
+// `MemorySpace::get` to `enumerate_with` to scan a set of buffers instead.
+#[derive(Clone, Debug, Default)]
+pub struct Memory {
+    data: Option<Rc<RefCell<Vec<u8>>>>,
+}
+
+impl Method for Memory {
+    // There is no buffer to look up, so this is always an absent function.
+    fn try_from(_address: PciAddress) -> Result<Self, error::Error> {
+        Ok(Memory::default())
+    }
+
+    fn config_size(&self) -> u16 {
+        match &self.data {
+            Some(data) => data.borrow().len() as u16,
+            None => CONFIG_SPACE_SIZE,
+        }
+    }
+
+    fn read8(&self, offset: u16) -> u8 {
+        let mut buf = [0xFFu8; 1];
+        self.read(offset, &mut buf);
+        buf[0]
+    }
+
+    fn read16(&self, offset: u16) -> u16 {
+        let mut buf = [0xFFu8; 2];
+        self.read(offset, &mut buf);
+        u16::from_le_bytes(buf)
+    }
+
+    fn read32(&self, offset: u16) -> u32 {
+        let mut buf = [0xFFu8; 4];
+        self.read(offset, &mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn write8(&self, offset: u16, value: u8) -> Result<(), error::Error> {
+        self.write(offset, &value.to_le_bytes())
+    }
+
+    fn write16(&self, offset: u16, value: u16) -> Result<(), error::Error> {
+        self.write(offset, &value.to_le_bytes())
+    }
+
+    fn write32(&self, offset: u16, value: u32) -> Result<(), error::Error> {
+        self.write(offset, &value.to_le_bytes())
+    }
+}
+
+impl Memory {
+    pub fn new(data: &[u8]) -> Result<Self, error::Error> {
+        let size = if data.len() <= CONFIG_SPACE_SIZE as usize {
+            CONFIG_SPACE_SIZE
+        } else if data.len() <= EXTENDED_CONFIG_SPACE_SIZE as usize {
+            EXTENDED_CONFIG_SPACE_SIZE
+        } else {
+            return Err(error::Error::InvalidLength(data.len()));
+        };
+
+        let mut buffer = data.to_vec();
+        buffer.resize(size as usize, 0);
+
+        Ok(Memory {
+            data: Some(Rc::new(RefCell::new(buffer))),
+        })
+    }
+
+    pub fn present(&self) -> bool {
+        self.data.is_some()
+    }
+
+    pub fn data(&self) -> Option<Vec<u8>> {
+        self.data.as_ref().map(|d| d.borrow().clone())
+    }
+
+    fn read(&self, offset: u16, buf: &mut [u8]) {
+        if let Some(data) = &self.data {
+            let s = offset as usize;
+            let e = s + buf.len();
+            if let Some(bytes) = data.borrow().get(s..e) {
+                buf.copy_from_slice(bytes);
+            }
+        }
+    }
+
+    fn write(&self, offset: u16, buf: &[u8]) -> Result<(), error::Error> {
+        // Writes to an absent function are dropped like on a real bus.
+        if let Some(data) = &self.data {
+            let s = offset as usize;
+            let e = s + buf.len();
+            let mut data = data.borrow_mut();
+            let bytes = data.get_mut(s..e).ok_or(error::Error::OutOfRange(offset))?;
+            bytes.copy_from_slice(buf);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemorySpace {
+    functions: BTreeMap<PciAddress, Memory>,
+}
+
+impl MemorySpace {
+    pub fn new() -> Self {
+        MemorySpace::default()
+    }
+
+    pub fn insert(&mut self, address: PciAddress, data: &[u8]) -> Result<(), error::Error> {
+        let memory = Memory::new(data)?;
+        self.functions.insert(address, memory);
+        Ok(())
+    }
+
+    pub fn get(&self, address: PciAddress) -> Memory {
+        self.functions.get(&address).cloned().unwrap_or_default()
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = PciAddress> + '_ {
+        self.functions.keys().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+}
@@ -0,0 +1,255 @@
+use super::capability::RawCapability;
+use super::{Method, PciConfigType0};
+
+pub const OFFSET_MESSAGE_CONTROL: u16 = 0x02;
+pub const OFFSET_MESSAGE_ADDRESS: u16 = 0x04;
+pub const OFFSET_MESSAGE_UPPER_ADDRESS: u16 = 0x08;
+
+pub const OFFSET_MSIX_TABLE: u16 = 0x04;
+pub const OFFSET_MSIX_PBA: u16 = 0x08;
+
+#[derive(Clone, Debug)]
+pub struct MsiCapability<T: Method> {
+    pub(crate) raw: RawCapability<T>,
+    message_control: MsiMessageControl,
+    message_address: u64,
+    message_data: u16,
+    mask_bits: u32,
+    pending_bits: u32,
+}
+
+impl<T: Method> MsiCapability<T> {
+    pub(crate) fn new(raw: RawCapability<T>) -> Self {
+        let message_control = MsiMessageControl(raw.read16(OFFSET_MESSAGE_CONTROL));
+
+        // The upper address dword shifts everything after it by four bytes.
+        let (message_address, data) = if message_control.is_64bit() {
+            let lower = raw.read32(OFFSET_MESSAGE_ADDRESS) as u64;
+            let upper = raw.read32(OFFSET_MESSAGE_UPPER_ADDRESS) as u64;
+            ((upper << 32) | lower, 0x0C)
+        } else {
+            (raw.read32(OFFSET_MESSAGE_ADDRESS) as u64, 0x08)
+        };
+
+        let (mask_bits, pending_bits) = if message_control.per_vector_masking() {
+            (raw.read32(data + 0x04), raw.read32(data + 0x08))
+        } else {
+            (0, 0)
+        };
+
+        MsiCapability {
+            message_control,
+            message_address,
+            message_data: raw.read16(data),
+            mask_bits,
+            pending_bits,
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn message_control(&self) -> MsiMessageControl {
+        self.message_control
+    }
+
+    pub fn message_address(&self) -> u64 {
+        self.message_address
+    }
+
+    pub fn message_data(&self) -> u16 {
+        self.message_data
+    }
+
+    pub fn mask_bits(&self) -> Option<u32> {
+        self.message_control
+            .per_vector_masking()
+            .then_some(self.mask_bits)
+    }
+
+    pub fn pending_bits(&self) -> Option<u32> {
+        self.message_control
+            .per_vector_masking()
+            .then_some(self.pending_bits)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MsiMessageControl(u16);
+
+impl MsiMessageControl {
+    pub fn enable(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    // Number of vectors, not the encoded exponent.
+    pub fn multiple_message_capable(&self) -> u8 {
+        1 << ((self.0 >> 1) & 0x07)
+    }
+
+    pub fn multiple_message_enable(&self) -> u8 {
+        1 << ((self.0 >> 4) & 0x07)
+    }
+
+    pub fn is_64bit(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    pub fn per_vector_masking(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    pub fn extended_message_data_capable(&self) -> bool {
+        self.get_bool(9)
+    }
+
+    pub fn extended_message_data_enable(&self) -> bool {
+        self.get_bool(10)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MsiXCapability<T: Method> {
+    pub(crate) raw: RawCapability<T>,
+    message_control: MsiXMessageControl,
+    table: u32,
+    pba: u32,
+}
+
+impl<T: Method> MsiXCapability<T> {
+    pub(crate) fn new(raw: RawCapability<T>) -> Self {
+        MsiXCapability {
+            message_control: MsiXMessageControl(raw.read16(OFFSET_MESSAGE_CONTROL)),
+            table: raw.read32(OFFSET_MSIX_TABLE),
+            pba: raw.read32(OFFSET_MSIX_PBA),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn message_control(&self) -> MsiXMessageControl {
+        self.message_control
+    }
+
+    pub fn table_bir(&self) -> u8 {
+        (self.table & 0x07) as u8
+    }
+
+    pub fn table_offset(&self) -> u32 {
+        self.table & !0x07
+    }
+
+    pub fn pba_bir(&self) -> u8 {
+        (self.pba & 0x07) as u8
+    }
+
+    pub fn pba_offset(&self) -> u32 {
+        self.pba & !0x07
+    }
+
+    pub fn table_address(&self, header: &PciConfigType0) -> Option<u64> {
+        resolve(header, self.table_bir(), self.table_offset())
+    }
+
+    pub fn pba_address(&self, header: &PciConfigType0) -> Option<u64> {
+        resolve(header, self.pba_bir(), self.pba_offset())
+    }
+}
+
+// An I/O or unassigned BAR cannot hold the table.
+fn resolve(header: &PciConfigType0, bir: u8, offset: u32) -> Option<u64> {
+    let bar = header.base_address(bir)?;
+    if bar.io_space() || bar.bar() == 0 {
+        return None;
+    }
+
+    Some(bar.bar() + offset as u64)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MsiXMessageControl(u16);
+
+impl MsiXMessageControl {
+    pub fn table_size(&self) -> u16 {
+        (self.0 & 0x07FF) + 1
+    }
+
+    pub fn function_mask(&self) -> bool {
+        (self.0 & 0x4000) == 0x4000
+    }
+
+    pub fn enable(&self) -> bool {
+        (self.0 & 0x8000) == 0x8000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::Capability;
+    use crate::testing::{config, function_with_cap};
+
+    #[test]
+    fn msi_and_msix() {
+        let mut data = function_with_cap(0x05, &[0x87, 0x01]);
+        data[0x10..0x14].copy_from_slice(&0xFE00_000Cu32.to_le_bytes());
+        data[0x14..0x18].copy_from_slice(&0x0000_0001u32.to_le_bytes());
+        data[0x18..0x1C].copy_from_slice(&0xFEB0_0000u32.to_le_bytes());
+        data[0x1C..0x20].copy_from_slice(&0x0000_E001u32.to_le_bytes());
+        data[0x41] = 0x60;
+        data[0x44..0x48].copy_from_slice(&0xFEE0_1000u32.to_le_bytes());
+        data[0x48..0x4C].copy_from_slice(&0x0000_0000u32.to_le_bytes());
+        data[0x4C..0x4E].copy_from_slice(&0x4021u16.to_le_bytes());
+        data[0x50..0x54].copy_from_slice(&0x0000_000Eu32.to_le_bytes());
+        data[0x54..0x58].copy_from_slice(&0x0000_0001u32.to_le_bytes());
+        data[0x60..0x64].copy_from_slice(&[0x11, 0x00, 0x3F, 0x80]);
+        data[0x64..0x68].copy_from_slice(&0x0000_2002u32.to_le_bytes());
+        data[0x68..0x6C].copy_from_slice(&0x0000_3000u32.to_le_bytes());
+
+        let cfg = config(&data);
+        let t0 = cfg.get_type0_header().unwrap();
+        let mut caps = cfg.capabilities().map(|c| c.decode());
+
+        let Some(Capability::Msi(msi)) = caps.next() else {
+            panic!("expected an MSI capability");
+        };
+        let ctl = msi.message_control();
+        assert!(ctl.enable());
+        assert!(ctl.is_64bit());
+        assert!(ctl.per_vector_masking());
+        assert_eq!(ctl.multiple_message_capable(), 8);
+        assert_eq!(ctl.multiple_message_enable(), 1);
+        assert_eq!(msi.message_address(), 0xFEE0_1000);
+        assert_eq!(msi.message_data(), 0x4021);
+        assert_eq!(msi.mask_bits(), Some(0x0E));
+        assert_eq!(msi.pending_bits(), Some(0x01));
+
+        let Some(Capability::MsiX(msix)) = caps.next() else {
+            panic!("expected an MSI-X capability");
+        };
+        let ctl = msix.message_control();
+        assert!(ctl.enable());
+        assert!(!ctl.function_mask());
+        assert_eq!(ctl.table_size(), 64);
+        assert_eq!(msix.table_bir(), 2);
+        assert_eq!(msix.table_offset(), 0x2000);
+        assert_eq!(msix.table_address(&t0), Some(0xFEB0_2000));
+        assert_eq!(msix.pba_bir(), 0);
+        assert_eq!(msix.pba_address(&t0), Some(0x0000_0001_FE00_3000));
+
+        // BAR 1 is the upper half of BAR 0 and BAR 3 is I/O.
+        assert!(t0.base_address(1).is_none());
+        assert!(resolve(&t0, 3, 0).is_none());
+        assert!(t0.base_address(6).is_none());
+    }
+}
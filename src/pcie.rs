@@ -0,0 +1,1083 @@
+use super::Method;
+use super::capability::RawCapability;
+
+pub const OFFSET_CAPABILITIES: u16 = 0x02;
+pub const OFFSET_DEVICE_CAPABILITIES: u16 = 0x04;
+pub const OFFSET_DEVICE_CONTROL: u16 = 0x08;
+pub const OFFSET_DEVICE_STATUS: u16 = 0x0A;
+pub const OFFSET_LINK_CAPABILITIES: u16 = 0x0C;
+pub const OFFSET_LINK_CONTROL: u16 = 0x10;
+pub const OFFSET_LINK_STATUS: u16 = 0x12;
+pub const OFFSET_SLOT_CAPABILITIES: u16 = 0x14;
+pub const OFFSET_SLOT_CONTROL: u16 = 0x18;
+pub const OFFSET_SLOT_STATUS: u16 = 0x1A;
+pub const OFFSET_ROOT_CONTROL: u16 = 0x1C;
+pub const OFFSET_ROOT_CAPABILITIES: u16 = 0x1E;
+pub const OFFSET_ROOT_STATUS: u16 = 0x20;
+pub const OFFSET_DEVICE_CAPABILITIES2: u16 = 0x24;
+pub const OFFSET_DEVICE_CONTROL2: u16 = 0x28;
+pub const OFFSET_LINK_CAPABILITIES2: u16 = 0x2C;
+pub const OFFSET_LINK_CONTROL2: u16 = 0x30;
+pub const OFFSET_LINK_STATUS2: u16 = 0x32;
+
+#[derive(Clone, Debug)]
+pub struct PciECapability<T: Method> {
+    pub(crate) raw: RawCapability<T>,
+    capabilities: PciECapabilities,
+    device_capabilities: DeviceCapabilities,
+    device_control: DeviceControl,
+    device_status: DeviceStatus,
+    link_capabilities: LinkCapabilities,
+    link_control: LinkControl,
+    link_status: LinkStatus,
+    slot_capabilities: SlotCapabilities,
+    slot_control: SlotControl,
+    slot_status: SlotStatus,
+    root_control: RootControl,
+    root_capabilities: RootCapabilities,
+    root_status: RootStatus,
+    device_capabilities2: DeviceCapabilities2,
+    device_control2: DeviceControl2,
+    link_capabilities2: LinkCapabilities2,
+    link_control2: LinkControl2,
+    link_status2: LinkStatus2,
+}
+
+impl<T: Method> PciECapability<T> {
+    pub(crate) fn new(raw: RawCapability<T>) -> Self {
+        PciECapability {
+            capabilities: PciECapabilities(raw.read16(OFFSET_CAPABILITIES)),
+            device_capabilities: DeviceCapabilities(raw.read32(OFFSET_DEVICE_CAPABILITIES)),
+            device_control: DeviceControl(raw.read16(OFFSET_DEVICE_CONTROL)),
+            device_status: DeviceStatus(raw.read16(OFFSET_DEVICE_STATUS)),
+            link_capabilities: LinkCapabilities(raw.read32(OFFSET_LINK_CAPABILITIES)),
+            link_control: LinkControl(raw.read16(OFFSET_LINK_CONTROL)),
+            link_status: LinkStatus(raw.read16(OFFSET_LINK_STATUS)),
+            slot_capabilities: SlotCapabilities(raw.read32(OFFSET_SLOT_CAPABILITIES)),
+            slot_control: SlotControl(raw.read16(OFFSET_SLOT_CONTROL)),
+            slot_status: SlotStatus(raw.read16(OFFSET_SLOT_STATUS)),
+            root_control: RootControl(raw.read16(OFFSET_ROOT_CONTROL)),
+            root_capabilities: RootCapabilities(raw.read16(OFFSET_ROOT_CAPABILITIES)),
+            root_status: RootStatus(raw.read32(OFFSET_ROOT_STATUS)),
+            device_capabilities2: DeviceCapabilities2(raw.read32(OFFSET_DEVICE_CAPABILITIES2)),
+            device_control2: DeviceControl2(raw.read16(OFFSET_DEVICE_CONTROL2)),
+            link_capabilities2: LinkCapabilities2(raw.read32(OFFSET_LINK_CAPABILITIES2)),
+            link_control2: LinkControl2(raw.read16(OFFSET_LINK_CONTROL2)),
+            link_status2: LinkStatus2(raw.read16(OFFSET_LINK_STATUS2)),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn capabilities(&self) -> PciECapabilities {
+        self.capabilities
+    }
+
+    pub fn version(&self) -> u8 {
+        self.capabilities.version()
+    }
+
+    pub fn device_port_type(&self) -> DevicePortType {
+        self.capabilities.device_port_type()
+    }
+
+    pub fn device_capabilities(&self) -> DeviceCapabilities {
+        self.device_capabilities
+    }
+
+    pub fn device_control(&self) -> DeviceControl {
+        self.device_control
+    }
+
+    pub fn device_status(&self) -> DeviceStatus {
+        self.device_status
+    }
+
+    pub fn link_capabilities(&self) -> LinkCapabilities {
+        self.link_capabilities
+    }
+
+    pub fn link_control(&self) -> LinkControl {
+        self.link_control
+    }
+
+    pub fn link_status(&self) -> LinkStatus {
+        self.link_status
+    }
+
+    // Only ports connected to a slot implement the slot registers.
+    pub fn slot_capabilities(&self) -> Option<SlotCapabilities> {
+        self.slot().then_some(self.slot_capabilities)
+    }
+
+    pub fn slot_control(&self) -> Option<SlotControl> {
+        self.slot().then_some(self.slot_control)
+    }
+
+    pub fn slot_status(&self) -> Option<SlotStatus> {
+        self.slot().then_some(self.slot_status)
+    }
+
+    pub fn root_control(&self) -> Option<RootControl> {
+        self.root().then_some(self.root_control)
+    }
+
+    pub fn root_capabilities(&self) -> Option<RootCapabilities> {
+        self.root().then_some(self.root_capabilities)
+    }
+
+    pub fn root_status(&self) -> Option<RootStatus> {
+        self.root().then_some(self.root_status)
+    }
+
+    // Version 1 capabilities end after the root registers.
+    pub fn device_capabilities2(&self) -> Option<DeviceCapabilities2> {
+        self.v2().then_some(self.device_capabilities2)
+    }
+
+    pub fn device_control2(&self) -> Option<DeviceControl2> {
+        self.v2().then_some(self.device_control2)
+    }
+
+    pub fn link_capabilities2(&self) -> Option<LinkCapabilities2> {
+        self.v2().then_some(self.link_capabilities2)
+    }
+
+    pub fn link_control2(&self) -> Option<LinkControl2> {
+        self.v2().then_some(self.link_control2)
+    }
+
+    pub fn link_status2(&self) -> Option<LinkStatus2> {
+        self.v2().then_some(self.link_status2)
+    }
+
+    fn slot(&self) -> bool {
+        self.capabilities.slot_implemented()
+    }
+
+    fn root(&self) -> bool {
+        matches!(
+            self.device_port_type(),
+            DevicePortType::RootPort | DevicePortType::RootComplexEventCollector
+        )
+    }
+
+    fn v2(&self) -> bool {
+        self.version() >= 2
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DevicePortType {
+    Endpoint,
+    LegacyEndpoint,
+    RootPort,
+    UpstreamPort,
+    DownstreamPort,
+    PciExpressToPciBridge,
+    PciToPciExpressBridge,
+    RootComplexIntegratedEndpoint,
+    RootComplexEventCollector,
+    Unknown(u8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkSpeed {
+    Gt2_5,
+    Gt5,
+    Gt8,
+    Gt16,
+    Gt32,
+    Gt64,
+    Unknown(u8),
+}
+
+impl LinkSpeed {
+    pub fn from(value: u8) -> Self {
+        match value {
+            1 => LinkSpeed::Gt2_5,
+            2 => LinkSpeed::Gt5,
+            3 => LinkSpeed::Gt8,
+            4 => LinkSpeed::Gt16,
+            5 => LinkSpeed::Gt32,
+            6 => LinkSpeed::Gt64,
+            v => LinkSpeed::Unknown(v),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aspm {
+    Disabled,
+    L0s,
+    L1,
+    L0sL1,
+}
+
+impl Aspm {
+    fn from(value: u8) -> Self {
+        match value & 0x03 {
+            0 => Aspm::Disabled,
+            1 => Aspm::L0s,
+            2 => Aspm::L1,
+            _ => Aspm::L0sL1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PciECapabilities(u16);
+
+impl PciECapabilities {
+    pub fn version(&self) -> u8 {
+        (self.0 & 0x000F) as u8
+    }
+
+    pub fn device_port_type(&self) -> DevicePortType {
+        match (self.0 >> 4) & 0x000F {
+            0x0 => DevicePortType::Endpoint,
+            0x1 => DevicePortType::LegacyEndpoint,
+            0x4 => DevicePortType::RootPort,
+            0x5 => DevicePortType::UpstreamPort,
+            0x6 => DevicePortType::DownstreamPort,
+            0x7 => DevicePortType::PciExpressToPciBridge,
+            0x8 => DevicePortType::PciToPciExpressBridge,
+            0x9 => DevicePortType::RootComplexIntegratedEndpoint,
+            0xA => DevicePortType::RootComplexEventCollector,
+            v => DevicePortType::Unknown(v as u8),
+        }
+    }
+
+    pub fn slot_implemented(&self) -> bool {
+        (self.0 & 0x0100) == 0x0100
+    }
+
+    pub fn interrupt_message_number(&self) -> u8 {
+        ((self.0 >> 9) & 0x001F) as u8
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceCapabilities(u32);
+
+impl DeviceCapabilities {
+    // In bytes.
+    pub fn max_payload_size_supported(&self) -> u16 {
+        128 << (self.0 & 0x07)
+    }
+
+    pub fn phantom_functions(&self) -> u8 {
+        ((self.0 >> 3) & 0x03) as u8
+    }
+
+    pub fn extended_tag_field(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn l0s_acceptable_latency(&self) -> u8 {
+        ((self.0 >> 6) & 0x07) as u8
+    }
+
+    pub fn l1_acceptable_latency(&self) -> u8 {
+        ((self.0 >> 9) & 0x07) as u8
+    }
+
+    pub fn role_based_error_reporting(&self) -> bool {
+        self.get_bool(15)
+    }
+
+    pub fn err_cor_subclass_capable(&self) -> bool {
+        self.get_bool(16)
+    }
+
+    pub fn captured_slot_power_limit_value(&self) -> u8 {
+        ((self.0 >> 18) & 0xFF) as u8
+    }
+
+    pub fn captured_slot_power_limit_scale(&self) -> u8 {
+        ((self.0 >> 26) & 0x03) as u8
+    }
+
+    pub fn function_level_reset_capable(&self) -> bool {
+        self.get_bool(28)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u32 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceControl(u16);
+
+impl DeviceControl {
+    pub fn correctable_error_reporting_enable(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn non_fatal_error_reporting_enable(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn fatal_error_reporting_enable(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn unsupported_request_reporting_enable(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn relaxed_ordering_enable(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    // In bytes.
+    pub fn max_payload_size(&self) -> u16 {
+        128 << ((self.0 >> 5) & 0x07)
+    }
+
+    pub fn extended_tag_field_enable(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    pub fn phantom_functions_enable(&self) -> bool {
+        self.get_bool(9)
+    }
+
+    pub fn aux_power_pm_enable(&self) -> bool {
+        self.get_bool(10)
+    }
+
+    pub fn no_snoop_enable(&self) -> bool {
+        self.get_bool(11)
+    }
+
+    // In bytes.
+    pub fn max_read_request_size(&self) -> u16 {
+        128 << ((self.0 >> 12) & 0x07)
+    }
+
+    // Bridge configuration retry enable on bridges, initiate FLR on endpoints.
+    pub fn bridge_retry_or_flr(&self) -> bool {
+        self.get_bool(15)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceStatus(u16);
+
+impl DeviceStatus {
+    pub fn correctable_error_detected(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn non_fatal_error_detected(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn fatal_error_detected(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn unsupported_request_detected(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn aux_power_detected(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn transactions_pending(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn emergency_power_reduction_detected(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LinkCapabilities(u32);
+
+impl LinkCapabilities {
+    pub fn max_link_speed(&self) -> LinkSpeed {
+        LinkSpeed::from((self.0 & 0x0F) as u8)
+    }
+
+    pub fn max_link_width(&self) -> u8 {
+        ((self.0 >> 4) & 0x3F) as u8
+    }
+
+    pub fn aspm_support(&self) -> Aspm {
+        Aspm::from((self.0 >> 10) as u8)
+    }
+
+    pub fn l0s_exit_latency(&self) -> u8 {
+        ((self.0 >> 12) & 0x07) as u8
+    }
+
+    pub fn l1_exit_latency(&self) -> u8 {
+        ((self.0 >> 15) & 0x07) as u8
+    }
+
+    pub fn clock_power_management(&self) -> bool {
+        self.get_bool(18)
+    }
+
+    pub fn surprise_down_error_reporting_capable(&self) -> bool {
+        self.get_bool(19)
+    }
+
+    pub fn data_link_layer_link_active_reporting_capable(&self) -> bool {
+        self.get_bool(20)
+    }
+
+    pub fn link_bandwidth_notification_capability(&self) -> bool {
+        self.get_bool(21)
+    }
+
+    pub fn aspm_optionality_compliance(&self) -> bool {
+        self.get_bool(22)
+    }
+
+    pub fn port_number(&self) -> u8 {
+        (self.0 >> 24) as u8
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u32 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LinkControl(u16);
+
+impl LinkControl {
+    pub fn aspm_control(&self) -> Aspm {
+        Aspm::from(self.0 as u8)
+    }
+
+    pub fn read_completion_boundary_128(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn link_disable(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn retrain_link(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn common_clock_configuration(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn extended_synch(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    pub fn enable_clock_power_management(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    pub fn hardware_autonomous_width_disable(&self) -> bool {
+        self.get_bool(9)
+    }
+
+    pub fn link_bandwidth_management_interrupt_enable(&self) -> bool {
+        self.get_bool(10)
+    }
+
+    pub fn link_autonomous_bandwidth_interrupt_enable(&self) -> bool {
+        self.get_bool(11)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LinkStatus(u16);
+
+impl LinkStatus {
+    pub fn current_link_speed(&self) -> LinkSpeed {
+        LinkSpeed::from((self.0 & 0x0F) as u8)
+    }
+
+    pub fn negotiated_link_width(&self) -> u8 {
+        ((self.0 >> 4) & 0x3F) as u8
+    }
+
+    pub fn link_training(&self) -> bool {
+        self.get_bool(11)
+    }
+
+    pub fn slot_clock_configuration(&self) -> bool {
+        self.get_bool(12)
+    }
+
+    pub fn data_link_layer_link_active(&self) -> bool {
+        self.get_bool(13)
+    }
+
+    pub fn link_bandwidth_management_status(&self) -> bool {
+        self.get_bool(14)
+    }
+
+    pub fn link_autonomous_bandwidth_status(&self) -> bool {
+        self.get_bool(15)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SlotCapabilities(u32);
+
+impl SlotCapabilities {
+    pub fn attention_button_present(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn power_controller_present(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn mrl_sensor_present(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn attention_indicator_present(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn power_indicator_present(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn hot_plug_surprise(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn hot_plug_capable(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn slot_power_limit_value(&self) -> u8 {
+        ((self.0 >> 7) & 0xFF) as u8
+    }
+
+    pub fn slot_power_limit_scale(&self) -> u8 {
+        ((self.0 >> 15) & 0x03) as u8
+    }
+
+    pub fn electromechanical_interlock_present(&self) -> bool {
+        self.get_bool(17)
+    }
+
+    pub fn no_command_completed_support(&self) -> bool {
+        self.get_bool(18)
+    }
+
+    pub fn physical_slot_number(&self) -> u16 {
+        (self.0 >> 19) as u16
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u32 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SlotControl(u16);
+
+impl SlotControl {
+    pub fn attention_button_pressed_enable(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn power_fault_detected_enable(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn mrl_sensor_changed_enable(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn presence_detect_changed_enable(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn command_completed_interrupt_enable(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn hot_plug_interrupt_enable(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn attention_indicator_control(&self) -> u8 {
+        ((self.0 >> 6) & 0x03) as u8
+    }
+
+    pub fn power_indicator_control(&self) -> u8 {
+        ((self.0 >> 8) & 0x03) as u8
+    }
+
+    // Set means power is off.
+    pub fn power_controller_control(&self) -> bool {
+        self.get_bool(10)
+    }
+
+    pub fn electromechanical_interlock_control(&self) -> bool {
+        self.get_bool(11)
+    }
+
+    pub fn data_link_layer_state_changed_enable(&self) -> bool {
+        self.get_bool(12)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SlotStatus(u16);
+
+impl SlotStatus {
+    pub fn attention_button_pressed(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn power_fault_detected(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn mrl_sensor_changed(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn presence_detect_changed(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn command_completed(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn mrl_sensor_state(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn presence_detect_state(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn electromechanical_interlock_status(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    pub fn data_link_layer_state_changed(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RootControl(u16);
+
+impl RootControl {
+    pub fn system_error_on_correctable_error_enable(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn system_error_on_non_fatal_error_enable(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn system_error_on_fatal_error_enable(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn pme_interrupt_enable(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn crs_software_visibility_enable(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RootCapabilities(u16);
+
+impl RootCapabilities {
+    pub fn crs_software_visibility(&self) -> bool {
+        (self.0 & 0x0001) == 0x0001
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RootStatus(u32);
+
+impl RootStatus {
+    pub fn pme_requester_id(&self) -> u16 {
+        self.0 as u16
+    }
+
+    pub fn pme_status(&self) -> bool {
+        self.get_bool(16)
+    }
+
+    pub fn pme_pending(&self) -> bool {
+        self.get_bool(17)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u32 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceCapabilities2(u32);
+
+impl DeviceCapabilities2 {
+    pub fn completion_timeout_ranges(&self) -> u8 {
+        (self.0 & 0x0F) as u8
+    }
+
+    pub fn completion_timeout_disable_supported(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn ari_forwarding_supported(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn atomic_op_routing_supported(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn atomic_op_32bit_completer_supported(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    pub fn atomic_op_64bit_completer_supported(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    pub fn cas_128bit_completer_supported(&self) -> bool {
+        self.get_bool(9)
+    }
+
+    pub fn no_ro_enabled_pr_pr_passing(&self) -> bool {
+        self.get_bool(10)
+    }
+
+    pub fn ltr_mechanism_supported(&self) -> bool {
+        self.get_bool(11)
+    }
+
+    pub fn tph_completer_supported(&self) -> u8 {
+        ((self.0 >> 12) & 0x03) as u8
+    }
+
+    pub fn tag_10bit_completer_supported(&self) -> bool {
+        self.get_bool(16)
+    }
+
+    pub fn tag_10bit_requester_supported(&self) -> bool {
+        self.get_bool(17)
+    }
+
+    pub fn obff_supported(&self) -> u8 {
+        ((self.0 >> 18) & 0x03) as u8
+    }
+
+    pub fn extended_fmt_field_supported(&self) -> bool {
+        self.get_bool(20)
+    }
+
+    pub fn end_end_tlp_prefix_supported(&self) -> bool {
+        self.get_bool(21)
+    }
+
+    pub fn max_end_end_tlp_prefixes(&self) -> u8 {
+        ((self.0 >> 22) & 0x03) as u8
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u32 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceControl2(u16);
+
+impl DeviceControl2 {
+    pub fn completion_timeout_value(&self) -> u8 {
+        (self.0 & 0x0F) as u8
+    }
+
+    pub fn completion_timeout_disable(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn ari_forwarding_enable(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn atomic_op_requester_enable(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn atomic_op_egress_blocking(&self) -> bool {
+        self.get_bool(7)
+    }
+
+    pub fn ido_request_enable(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    pub fn ido_completion_enable(&self) -> bool {
+        self.get_bool(9)
+    }
+
+    pub fn ltr_mechanism_enable(&self) -> bool {
+        self.get_bool(10)
+    }
+
+    pub fn tag_10bit_requester_enable(&self) -> bool {
+        self.get_bool(12)
+    }
+
+    pub fn obff_enable(&self) -> u8 {
+        ((self.0 >> 13) & 0x03) as u8
+    }
+
+    pub fn end_end_tlp_prefix_blocking(&self) -> bool {
+        self.get_bool(15)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LinkCapabilities2(u32);
+
+impl LinkCapabilities2 {
+    // Bit 0 is 2.5 GT/s, bit 1 is 5.0 GT/s and so on.
+    pub fn supported_link_speeds(&self) -> u8 {
+        ((self.0 >> 1) & 0x7F) as u8
+    }
+
+    pub fn crosslink_supported(&self) -> bool {
+        (self.0 & 0x0100) == 0x0100
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LinkControl2(u16);
+
+impl LinkControl2 {
+    pub fn target_link_speed(&self) -> LinkSpeed {
+        LinkSpeed::from((self.0 & 0x0F) as u8)
+    }
+
+    pub fn enter_compliance(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn hardware_autonomous_speed_disable(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    pub fn selectable_de_emphasis(&self) -> bool {
+        self.get_bool(6)
+    }
+
+    pub fn transmit_margin(&self) -> u8 {
+        ((self.0 >> 7) & 0x07) as u8
+    }
+
+    pub fn enter_modified_compliance(&self) -> bool {
+        self.get_bool(10)
+    }
+
+    pub fn compliance_sos(&self) -> bool {
+        self.get_bool(11)
+    }
+
+    pub fn compliance_preset(&self) -> u8 {
+        ((self.0 >> 12) & 0x0F) as u8
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LinkStatus2(u16);
+
+impl LinkStatus2 {
+    pub fn current_de_emphasis_level(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn equalization_complete(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn equalization_phase1_successful(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn equalization_phase2_successful(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn equalization_phase3_successful(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn link_equalization_request(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::Capability;
+    use crate::testing::{config, function_with_cap};
+
+    #[test]
+    fn root_port() {
+        let mut data = function_with_cap(0x10, &[0x42, 0x01]);
+        data[0x0E] = 0x01;
+        data[0x44..0x48].copy_from_slice(&0x1000_8022u32.to_le_bytes());
+        data[0x48..0x4A].copy_from_slice(&0x2837u16.to_le_bytes());
+        data[0x4A..0x4C].copy_from_slice(&0x0009u16.to_le_bytes());
+        data[0x4C..0x50].copy_from_slice(&0x0473_CC84u32.to_le_bytes());
+        data[0x50..0x52].copy_from_slice(&0x0042u16.to_le_bytes());
+        data[0x52..0x54].copy_from_slice(&0x7043u16.to_le_bytes());
+        data[0x54..0x58].copy_from_slice(&0x0038_0060u32.to_le_bytes());
+        data[0x5A..0x5C].copy_from_slice(&0x0040u16.to_le_bytes());
+        data[0x5C..0x5E].copy_from_slice(&0x0008u16.to_le_bytes());
+        data[0x64..0x68].copy_from_slice(&0x0003_0820u32.to_le_bytes());
+        data[0x6C..0x70].copy_from_slice(&0x0000_001Eu32.to_le_bytes());
+        data[0x70..0x72].copy_from_slice(&0x0004u16.to_le_bytes());
+        data[0x72..0x74].copy_from_slice(&0x000Eu16.to_le_bytes());
+
+        let cfg = config(&data);
+        let Some(Capability::PciE(pcie)) = cfg.capabilities().next().map(|c| c.decode()) else {
+            panic!("expected a PCI Express capability");
+        };
+
+        assert_eq!(pcie.version(), 2);
+        assert_eq!(pcie.device_port_type(), DevicePortType::RootPort);
+
+        let devcap = pcie.device_capabilities();
+        assert_eq!(devcap.max_payload_size_supported(), 512);
+        assert!(devcap.extended_tag_field());
+        assert!(devcap.function_level_reset_capable());
+
+        let devctl = pcie.device_control();
+        assert!(devctl.correctable_error_reporting_enable());
+        assert!(devctl.non_fatal_error_reporting_enable());
+        assert!(devctl.fatal_error_reporting_enable());
+        assert!(!devctl.unsupported_request_reporting_enable());
+        assert_eq!(devctl.max_payload_size(), 256);
+        assert_eq!(devctl.max_read_request_size(), 512);
+
+        let devsta = pcie.device_status();
+        assert!(devsta.correctable_error_detected());
+        assert!(devsta.unsupported_request_detected());
+
+        let lnkcap = pcie.link_capabilities();
+        assert_eq!(lnkcap.max_link_speed(), LinkSpeed::Gt16);
+        assert_eq!(lnkcap.max_link_width(), 8);
+        assert_eq!(lnkcap.aspm_support(), Aspm::L0sL1);
+        assert_eq!(lnkcap.port_number(), 4);
+        assert!(lnkcap.data_link_layer_link_active_reporting_capable());
+
+        let lnkctl = pcie.link_control();
+        assert_eq!(lnkctl.aspm_control(), Aspm::L1);
+        assert!(lnkctl.common_clock_configuration());
+
+        let lnksta = pcie.link_status();
+        assert_eq!(lnksta.current_link_speed(), LinkSpeed::Gt8);
+        assert_eq!(lnksta.negotiated_link_width(), 4);
+        assert!(!lnksta.link_training());
+        assert!(lnksta.data_link_layer_link_active());
+
+        let sltcap = pcie.slot_capabilities().unwrap();
+        assert!(sltcap.hot_plug_capable());
+        assert_eq!(sltcap.physical_slot_number(), 7);
+        assert!(pcie.slot_status().unwrap().presence_detect_state());
+        assert!(pcie.root_control().unwrap().pme_interrupt_enable());
+
+        let devcap2 = pcie.device_capabilities2().unwrap();
+        assert!(devcap2.ari_forwarding_supported());
+        assert!(devcap2.ltr_mechanism_supported());
+        assert!(devcap2.tag_10bit_requester_supported());
+        assert_eq!(
+            pcie.link_capabilities2().unwrap().supported_link_speeds(),
+            0x0F
+        );
+        assert_eq!(
+            pcie.link_control2().unwrap().target_link_speed(),
+            LinkSpeed::Gt16
+        );
+        assert!(
+            pcie.link_status2()
+                .unwrap()
+                .equalization_phase1_successful()
+        );
+    }
+}
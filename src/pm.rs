@@ -0,0 +1,251 @@
+use super::Method;
+use super::capability::RawCapability;
+use super::error;
+use std::thread;
+use std::time::Duration;
+
+pub const OFFSET_PMC: u16 = 0x02;
+pub const OFFSET_PMCSR: u16 = 0x04;
+pub const OFFSET_PMCSR_BSE: u16 = 0x06;
+pub const OFFSET_DATA: u16 = 0x07;
+
+// Recovery times from the PCI Bus Power Management Interface spec.
+const D3HOT_DELAY: Duration = Duration::from_millis(10);
+const D2_DELAY: Duration = Duration::from_micros(200);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerState {
+    D0,
+    D1,
+    D2,
+    D3Hot,
+}
+
+impl PowerState {
+    fn from(value: u16) -> Self {
+        match value & 0x03 {
+            0 => PowerState::D0,
+            1 => PowerState::D1,
+            2 => PowerState::D2,
+            _ => PowerState::D3Hot,
+        }
+    }
+
+    fn bits(&self) -> u16 {
+        match self {
+            PowerState::D0 => 0,
+            PowerState::D1 => 1,
+            PowerState::D2 => 2,
+            PowerState::D3Hot => 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PmCapability<T: Method> {
+    pub(crate) raw: RawCapability<T>,
+    capabilities: PmCapabilities,
+    control_status: PmControlStatus,
+    bridge_extensions: u8,
+    data: u8,
+}
+
+impl<T: Method> PmCapability<T> {
+    pub(crate) fn new(raw: RawCapability<T>) -> Self {
+        PmCapability {
+            capabilities: PmCapabilities(raw.read16(OFFSET_PMC)),
+            control_status: PmControlStatus(raw.read16(OFFSET_PMCSR)),
+            bridge_extensions: raw.read8(OFFSET_PMCSR_BSE),
+            data: raw.read8(OFFSET_DATA),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &RawCapability<T> {
+        &self.raw
+    }
+
+    pub fn version(&self) -> u8 {
+        self.capabilities.version()
+    }
+
+    pub fn capabilities(&self) -> PmCapabilities {
+        self.capabilities
+    }
+
+    pub fn control_status(&self) -> PmControlStatus {
+        self.control_status
+    }
+
+    pub fn bridge_extensions(&self) -> u8 {
+        self.bridge_extensions
+    }
+
+    pub fn data(&self) -> u8 {
+        self.data
+    }
+
+    // Moves the function to `state` and waits out the recovery time before returning.
+    // The decoded registers are a snapshot, so decode the capability again to see the result.
+    pub fn set_power_state(&self, state: PowerState) -> Result<(), error::Error> {
+        let supported = match state {
+            PowerState::D1 => self.capabilities.d1_support(),
+            PowerState::D2 => self.capabilities.d2_support(),
+            _ => true,
+        };
+        if !supported {
+            return Err(error::Error::UnsupportedPowerState);
+        }
+
+        let current = self.raw.read16(OFFSET_PMCSR);
+        let from = PowerState::from(current);
+        if from == state {
+            return Ok(());
+        }
+
+        // Writing PME_Status back would clear it.
+        let value = (current & !0x8003) | state.bits();
+        self.raw.write16(OFFSET_PMCSR, value)?;
+
+        if from == PowerState::D3Hot || state == PowerState::D3Hot {
+            thread::sleep(D3HOT_DELAY);
+        } else if from == PowerState::D2 || state == PowerState::D2 {
+            thread::sleep(D2_DELAY);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PmCapabilities(u16);
+
+impl PmCapabilities {
+    pub fn version(&self) -> u8 {
+        (self.0 & 0x07) as u8
+    }
+
+    pub fn pme_clock(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn immediate_readiness_on_return_to_d0(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn device_specific_initialization(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    // In mA.
+    pub fn aux_current(&self) -> u16 {
+        match (self.0 >> 6) & 0x07 {
+            0 => 0,
+            1 => 55,
+            2 => 100,
+            3 => 160,
+            4 => 220,
+            5 => 270,
+            6 => 320,
+            _ => 375,
+        }
+    }
+
+    pub fn d1_support(&self) -> bool {
+        self.get_bool(9)
+    }
+
+    pub fn d2_support(&self) -> bool {
+        self.get_bool(10)
+    }
+
+    pub fn pme_support(&self, state: PowerState) -> bool {
+        self.get_bool(11 + state.bits() as u8)
+    }
+
+    pub fn pme_support_d3cold(&self) -> bool {
+        self.get_bool(15)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PmControlStatus(u16);
+
+impl PmControlStatus {
+    pub fn power_state(&self) -> PowerState {
+        PowerState::from(self.0)
+    }
+
+    pub fn no_soft_reset(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn pme_enable(&self) -> bool {
+        self.get_bool(8)
+    }
+
+    pub fn data_select(&self) -> u8 {
+        ((self.0 >> 9) & 0x0F) as u8
+    }
+
+    pub fn data_scale(&self) -> u8 {
+        ((self.0 >> 13) & 0x03) as u8
+    }
+
+    pub fn pme_status(&self) -> bool {
+        self.get_bool(15)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability::Capability;
+    use crate::testing::config_with_cap;
+
+    #[test]
+    fn d_states() {
+        let cfg = config_with_cap(0x01, &[0x43, 0xC8, 0x08, 0x80]);
+        let Some(Capability::Pm(cap)) = cfg.capabilities().next().map(|c| c.decode()) else {
+            panic!("expected a power management capability");
+        };
+        let pmc = cap.capabilities();
+        assert_eq!(cap.version(), 3);
+        assert_eq!(pmc.aux_current(), 55);
+        assert!(!pmc.d1_support());
+        assert!(!pmc.d2_support());
+        assert!(pmc.pme_support(PowerState::D0));
+        assert!(!pmc.pme_support(PowerState::D1));
+        assert!(pmc.pme_support(PowerState::D3Hot));
+        assert!(pmc.pme_support_d3cold());
+
+        let pmcsr = cap.control_status();
+        assert_eq!(pmcsr.power_state(), PowerState::D0);
+        assert!(pmcsr.no_soft_reset());
+        assert!(pmcsr.pme_status());
+
+        assert!(matches!(
+            cap.set_power_state(PowerState::D1),
+            Err(error::Error::UnsupportedPowerState)
+        ));
+
+        cap.set_power_state(PowerState::D3Hot).unwrap();
+        let pmcsr = cap.raw().read16(OFFSET_PMCSR);
+        assert_eq!(pmcsr, 0x000B);
+
+        cap.set_power_state(PowerState::D0).unwrap();
+        let pmcsr = PmControlStatus(cap.raw().read16(OFFSET_PMCSR));
+        assert_eq!(pmcsr.power_state(), PowerState::D0);
+        assert!(!pmcsr.pme_status());
+    }
+}
@@ -0,0 +1,408 @@
+use super::error;
+
+pub const ROM_SIGNATURE: u16 = 0xAA55;
+pub const PCIR_SIGNATURE: &[u8; 4] = b"PCIR";
+pub const EFI_SIGNATURE: u32 = 0x0EF1;
+
+pub const OFFSET_SIGNATURE: usize = 0x00;
+pub const OFFSET_INITIALIZATION_SIZE: usize = 0x02;
+pub const OFFSET_EFI_SIGNATURE: usize = 0x04;
+pub const OFFSET_EFI_SUBSYSTEM: usize = 0x08;
+pub const OFFSET_EFI_MACHINE_TYPE: usize = 0x0A;
+pub const OFFSET_EFI_COMPRESSION_TYPE: usize = 0x0C;
+pub const OFFSET_EFI_IMAGE_OFFSET: usize = 0x16;
+pub const OFFSET_PCIR_POINTER: usize = 0x18;
+
+pub const OFFSET_PCIR_VENDOR_ID: usize = 0x04;
+pub const OFFSET_PCIR_DEVICE_ID: usize = 0x06;
+pub const OFFSET_PCIR_DEVICE_LIST_POINTER: usize = 0x08;
+pub const OFFSET_PCIR_LENGTH: usize = 0x0A;
+pub const OFFSET_PCIR_REVISION: usize = 0x0C;
+pub const OFFSET_PCIR_CLASS_CODE: usize = 0x0D;
+pub const OFFSET_PCIR_IMAGE_LENGTH: usize = 0x10;
+pub const OFFSET_PCIR_CODE_REVISION: usize = 0x12;
+pub const OFFSET_PCIR_CODE_TYPE: usize = 0x14;
+pub const OFFSET_PCIR_INDICATOR: usize = 0x15;
+pub const OFFSET_PCIR_MAX_RUNTIME_IMAGE_LENGTH: usize = 0x16;
+pub const OFFSET_PCIR_CONFIG_UTILITY_POINTER: usize = 0x18;
+pub const OFFSET_PCIR_DMTF_CLP_POINTER: usize = 0x1A;
+
+// Image lengths are counted in 512-byte units.
+const BLOCK_SIZE: usize = 512;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodeType {
+    X86,
+    OpenFirmware,
+    HpPaRisc,
+    Efi,
+    Unknown(u8),
+}
+
+impl CodeType {
+    pub fn from(value: u8) -> Self {
+        match value {
+            0x00 => CodeType::X86,
+            0x01 => CodeType::OpenFirmware,
+            0x02 => CodeType::HpPaRisc,
+            0x03 => CodeType::Efi,
+            _ => CodeType::Unknown(value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EfiSubsystem {
+    Application,
+    BootServiceDriver,
+    RuntimeDriver,
+    Unknown(u16),
+}
+
+impl EfiSubsystem {
+    pub fn from(value: u16) -> Self {
+        match value {
+            10 => EfiSubsystem::Application,
+            11 => EfiSubsystem::BootServiceDriver,
+            12 => EfiSubsystem::RuntimeDriver,
+            _ => EfiSubsystem::Unknown(value),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EfiMachineType {
+    Ia32,
+    Ia64,
+    Ebc,
+    X64,
+    Arm,
+    Aarch64,
+    RiscV64,
+    LoongArch64,
+    Unknown(u16),
+}
+
+impl EfiMachineType {
+    pub fn from(value: u16) -> Self {
+        match value {
+            0x014C => EfiMachineType::Ia32,
+            0x0200 => EfiMachineType::Ia64,
+            0x0EBC => EfiMachineType::Ebc,
+            0x8664 => EfiMachineType::X64,
+            0x01C2 => EfiMachineType::Arm,
+            0xAA64 => EfiMachineType::Aarch64,
+            0x5064 => EfiMachineType::RiscV64,
+            0x6264 => EfiMachineType::LoongArch64,
+            _ => EfiMachineType::Unknown(value),
+        }
+    }
+}
+
+// Fields of the ROM header that only EFI images have.
+#[derive(Clone, Copy, Debug)]
+pub struct EfiHeader {
+    subsystem: EfiSubsystem,
+    machine_type: EfiMachineType,
+    compressed: bool,
+    image_offset: u16,
+}
+
+impl EfiHeader {
+    pub fn subsystem(&self) -> EfiSubsystem {
+        self.subsystem
+    }
+
+    pub fn machine_type(&self) -> EfiMachineType {
+        self.machine_type
+    }
+
+    pub fn compressed(&self) -> bool {
+        self.compressed
+    }
+
+    // Relative to the start of the ROM image.
+    pub fn image_offset(&self) -> u16 {
+        self.image_offset
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RomImage {
+    offset: usize,
+    data: Vec<u8>,
+    initialization_size: usize,
+    vendor_id: u16,
+    device_id: u16,
+    device_list_pointer: u16,
+    pcir_revision: u8,
+    class_code: u32,
+    code_revision: u16,
+    code_type: CodeType,
+    indicator: u8,
+    max_runtime_image_length: usize,
+    config_utility_pointer: u16,
+    dmtf_clp_pointer: u16,
+    efi: Option<EfiHeader>,
+}
+
+impl RomImage {
+    fn parse(rom: &[u8], offset: usize) -> Result<Self, error::Error> {
+        let invalid = || error::Error::InvalidRomImage(offset as u32);
+        let header = &rom[offset..];
+
+        if read16(header, OFFSET_SIGNATURE) != Some(ROM_SIGNATURE) {
+            return Err(invalid());
+        }
+
+        let pcir_pointer = read16(header, OFFSET_PCIR_POINTER).ok_or_else(invalid)? as usize;
+        let pcir = header.get(pcir_pointer..).ok_or_else(invalid)?;
+        if !pcir.starts_with(PCIR_SIGNATURE) {
+            return Err(invalid());
+        }
+
+        let field8 = |at: usize| pcir.get(at).copied().ok_or_else(invalid);
+        let field16 = |at: usize| read16(pcir, at).ok_or_else(invalid);
+
+        let image_length = field16(OFFSET_PCIR_IMAGE_LENGTH)? as usize * BLOCK_SIZE;
+        if image_length == 0 || image_length > header.len() {
+            return Err(invalid());
+        }
+
+        let class_code = field16(OFFSET_PCIR_CLASS_CODE)? as u32
+            | (field8(OFFSET_PCIR_CLASS_CODE + 2)? as u32) << 16;
+        let code_type = CodeType::from(field8(OFFSET_PCIR_CODE_TYPE)?);
+
+        // Revision 3.0 added the runtime length and the two pointers after the indicator.
+        let pcir_length = field16(OFFSET_PCIR_LENGTH)? as usize;
+        let extended = |at: usize| {
+            if at + 2 <= pcir_length {
+                read16(pcir, at).unwrap_or(0)
+            } else {
+                0
+            }
+        };
+
+        // The header is known to span at least one block from here on.
+        let header16 = |at: usize| read16(header, at).unwrap_or(0);
+        let efi = match (code_type, read32(header, OFFSET_EFI_SIGNATURE)) {
+            (CodeType::Efi, Some(EFI_SIGNATURE)) => Some(EfiHeader {
+                subsystem: EfiSubsystem::from(header16(OFFSET_EFI_SUBSYSTEM)),
+                machine_type: EfiMachineType::from(header16(OFFSET_EFI_MACHINE_TYPE)),
+                compressed: header16(OFFSET_EFI_COMPRESSION_TYPE) == 1,
+                image_offset: header16(OFFSET_EFI_IMAGE_OFFSET),
+            }),
+            _ => None,
+        };
+
+        // Legacy images keep a one byte initialization size, EFI images widen it to two.
+        let initialization_size = match efi {
+            Some(_) => header16(OFFSET_INITIALIZATION_SIZE) as usize,
+            None => header[OFFSET_INITIALIZATION_SIZE] as usize,
+        } * BLOCK_SIZE;
+
+        Ok(RomImage {
+            offset,
+            data: header[..image_length].to_vec(),
+            initialization_size,
+            vendor_id: field16(OFFSET_PCIR_VENDOR_ID)?,
+            device_id: field16(OFFSET_PCIR_DEVICE_ID)?,
+            device_list_pointer: field16(OFFSET_PCIR_DEVICE_LIST_POINTER)?,
+            pcir_revision: field8(OFFSET_PCIR_REVISION)?,
+            class_code,
+            code_revision: field16(OFFSET_PCIR_CODE_REVISION)?,
+            code_type,
+            indicator: field8(OFFSET_PCIR_INDICATOR)?,
+            max_runtime_image_length: extended(OFFSET_PCIR_MAX_RUNTIME_IMAGE_LENGTH) as usize
+                * BLOCK_SIZE,
+            config_utility_pointer: extended(OFFSET_PCIR_CONFIG_UTILITY_POINTER),
+            dmtf_clp_pointer: extended(OFFSET_PCIR_DMTF_CLP_POINTER),
+            efi,
+        })
+    }
+
+    // Relative to the start of the ROM.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn image_length(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn initialization_size(&self) -> usize {
+        self.initialization_size
+    }
+
+    pub fn vendor_id(&self) -> u16 {
+        self.vendor_id
+    }
+
+    pub fn device_id(&self) -> u16 {
+        self.device_id
+    }
+
+    pub fn device_list_pointer(&self) -> u16 {
+        self.device_list_pointer
+    }
+
+    pub fn pcir_revision(&self) -> u8 {
+        self.pcir_revision
+    }
+
+    pub fn class_code(&self) -> u32 {
+        self.class_code
+    }
+
+    // Vendor defined revision of the code in the image.
+    pub fn code_revision(&self) -> u16 {
+        self.code_revision
+    }
+
+    pub fn code_type(&self) -> CodeType {
+        self.code_type
+    }
+
+    pub fn last_image(&self) -> bool {
+        (self.indicator & 0x80) == 0x80
+    }
+
+    pub fn max_runtime_image_length(&self) -> usize {
+        self.max_runtime_image_length
+    }
+
+    pub fn config_utility_pointer(&self) -> u16 {
+        self.config_utility_pointer
+    }
+
+    pub fn dmtf_clp_pointer(&self) -> u16 {
+        self.dmtf_clp_pointer
+    }
+
+    pub fn efi(&self) -> Option<EfiHeader> {
+        self.efi
+    }
+
+    // The bytes of a legacy image add up to zero. EFI images are not required to.
+    pub fn checksum(&self) -> u8 {
+        self.data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+    }
+
+    pub fn checksum_valid(&self) -> bool {
+        self.checksum() == 0
+    }
+}
+
+// Walks the image chain up to the image flagged as the last one.
+pub fn parse(rom: &[u8]) -> Result<Vec<RomImage>, error::Error> {
+    let mut images = vec![];
+
+    let mut offset = 0;
+    while offset < rom.len() {
+        let image = RomImage::parse(rom, offset)?;
+        offset += image.image_length();
+
+        let last = image.last_image();
+        images.push(image);
+        if last {
+            break;
+        }
+    }
+
+    Ok(images)
+}
+
+fn read16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(code_type: u8, last: bool, pcir: usize) -> Vec<u8> {
+        let mut data = vec![0u8; BLOCK_SIZE];
+        data[0x00..0x02].copy_from_slice(&ROM_SIGNATURE.to_le_bytes());
+        data[0x02] = 0x01;
+        data[0x18..0x1A].copy_from_slice(&(pcir as u16).to_le_bytes());
+        data[pcir..pcir + 4].copy_from_slice(PCIR_SIGNATURE);
+        data[pcir + 0x04..pcir + 0x08].copy_from_slice(&[0x86, 0x80, 0x33, 0x15]);
+        data[pcir + 0x0A..pcir + 0x0C].copy_from_slice(&0x001Cu16.to_le_bytes());
+        data[pcir + 0x0C] = 0x03;
+        data[pcir + 0x0D..pcir + 0x10].copy_from_slice(&[0x00, 0x00, 0x02]);
+        data[pcir + 0x10..pcir + 0x12].copy_from_slice(&0x0001u16.to_le_bytes());
+        data[pcir + 0x12..pcir + 0x14].copy_from_slice(&0x0165u16.to_le_bytes());
+        data[pcir + 0x14] = code_type;
+        data[pcir + 0x15] = if last { 0x80 } else { 0x00 };
+        data[pcir + 0x16..pcir + 0x18].copy_from_slice(&0x0001u16.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn image_chain() {
+        let mut legacy = image(0x00, false, 0x1C);
+        let sum = legacy.iter().fold(0u8, |s, b| s.wrapping_add(*b));
+        legacy[BLOCK_SIZE - 1] = sum.wrapping_neg();
+
+        let mut efi = image(0x03, true, 0x1C);
+        efi[0x04..0x08].copy_from_slice(&EFI_SIGNATURE.to_le_bytes());
+        efi[0x08..0x0A].copy_from_slice(&11u16.to_le_bytes());
+        efi[0x0A..0x0C].copy_from_slice(&0x8664u16.to_le_bytes());
+        efi[0x0C..0x0E].copy_from_slice(&1u16.to_le_bytes());
+        efi[0x16..0x18].copy_from_slice(&0x0040u16.to_le_bytes());
+
+        let mut rom = [legacy, efi].concat();
+        // Padding up to the BAR size is not part of any image.
+        rom.resize(4 * BLOCK_SIZE, 0xFF);
+
+        let images = parse(&rom).unwrap();
+        assert_eq!(images.len(), 2);
+
+        let legacy = &images[0];
+        assert_eq!(legacy.offset(), 0);
+        assert_eq!(legacy.code_type(), CodeType::X86);
+        assert_eq!(legacy.vendor_id(), 0x8086);
+        assert_eq!(legacy.device_id(), 0x1533);
+        assert_eq!(legacy.class_code(), 0x02_0000);
+        assert_eq!(legacy.pcir_revision(), 3);
+        assert_eq!(legacy.code_revision(), 0x0165);
+        assert_eq!(legacy.image_length(), BLOCK_SIZE);
+        assert_eq!(legacy.initialization_size(), BLOCK_SIZE);
+        assert_eq!(legacy.max_runtime_image_length(), BLOCK_SIZE);
+        assert!(!legacy.last_image());
+        assert!(legacy.efi().is_none());
+        assert!(legacy.checksum_valid());
+
+        let efi = &images[1];
+        assert_eq!(efi.offset(), BLOCK_SIZE);
+        assert_eq!(efi.code_type(), CodeType::Efi);
+        assert!(efi.last_image());
+        assert!(!efi.checksum_valid());
+        let header = efi.efi().unwrap();
+        assert_eq!(header.subsystem(), EfiSubsystem::BootServiceDriver);
+        assert_eq!(header.machine_type(), EfiMachineType::X64);
+        assert!(header.compressed());
+        assert_eq!(header.image_offset(), 0x40);
+
+        assert!(matches!(
+            parse(&rom[BLOCK_SIZE * 2..]),
+            Err(error::Error::InvalidRomImage(0))
+        ));
+
+        // The legacy image claims more data than the ROM holds.
+        assert!(matches!(
+            parse(&rom[..BLOCK_SIZE - 1]),
+            Err(error::Error::InvalidRomImage(0))
+        ));
+    }
+}
@@ -0,0 +1,288 @@
+use super::error;
+use super::{Method, PciAddress, PciBaseAddress, PciExtendedCapability, base_address};
+
+pub const OFFSET_CAPABILITIES: u16 = 0x04;
+pub const OFFSET_CONTROL: u16 = 0x08;
+pub const OFFSET_STATUS: u16 = 0x0A;
+pub const OFFSET_INITIAL_VFS: u16 = 0x0C;
+pub const OFFSET_TOTAL_VFS: u16 = 0x0E;
+pub const OFFSET_NUM_VFS: u16 = 0x10;
+pub const OFFSET_FUNCTION_DEPENDENCY_LINK: u16 = 0x12;
+pub const OFFSET_FIRST_VF_OFFSET: u16 = 0x14;
+pub const OFFSET_VF_STRIDE: u16 = 0x16;
+pub const OFFSET_VF_DEVICE_ID: u16 = 0x1A;
+pub const OFFSET_SUPPORTED_PAGE_SIZES: u16 = 0x1C;
+pub const OFFSET_SYSTEM_PAGE_SIZE: u16 = 0x20;
+pub const OFFSET_VF_BAR0: u16 = 0x24;
+pub const OFFSET_VF_MIGRATION_STATE: u16 = 0x3C;
+
+#[derive(Clone, Debug)]
+pub struct SrIovCapability<T: Method> {
+    pub(crate) raw: PciExtendedCapability<T>,
+    capabilities: SrIovCapabilities,
+    control: SrIovControl,
+    status: u16,
+    initial_vfs: u16,
+    total_vfs: u16,
+    num_vfs: u16,
+    function_dependency_link: u8,
+    first_vf_offset: u16,
+    vf_stride: u16,
+    vf_device_id: u16,
+    supported_page_sizes: u32,
+    system_page_size: u32,
+    vf_bars: [u32; 6],
+    vf_migration_state: u32,
+}
+
+impl<T: Method> SrIovCapability<T> {
+    pub(crate) fn new(raw: PciExtendedCapability<T>) -> Self {
+        let mut vf_bars = [0; 6];
+        for (i, bar) in vf_bars.iter_mut().enumerate() {
+            *bar = raw.read32(OFFSET_VF_BAR0 + i as u16 * 4);
+        }
+
+        SrIovCapability {
+            capabilities: SrIovCapabilities(raw.read32(OFFSET_CAPABILITIES)),
+            control: SrIovControl(raw.read16(OFFSET_CONTROL)),
+            status: raw.read16(OFFSET_STATUS),
+            initial_vfs: raw.read16(OFFSET_INITIAL_VFS),
+            total_vfs: raw.read16(OFFSET_TOTAL_VFS),
+            num_vfs: raw.read16(OFFSET_NUM_VFS),
+            function_dependency_link: raw.read8(OFFSET_FUNCTION_DEPENDENCY_LINK),
+            first_vf_offset: raw.read16(OFFSET_FIRST_VF_OFFSET),
+            vf_stride: raw.read16(OFFSET_VF_STRIDE),
+            vf_device_id: raw.read16(OFFSET_VF_DEVICE_ID),
+            supported_page_sizes: raw.read32(OFFSET_SUPPORTED_PAGE_SIZES),
+            system_page_size: raw.read32(OFFSET_SYSTEM_PAGE_SIZE),
+            vf_bars,
+            vf_migration_state: raw.read32(OFFSET_VF_MIGRATION_STATE),
+            raw,
+        }
+    }
+
+    pub fn raw(&self) -> &PciExtendedCapability<T> {
+        &self.raw
+    }
+
+    pub fn capabilities(&self) -> SrIovCapabilities {
+        self.capabilities
+    }
+
+    pub fn control(&self) -> SrIovControl {
+        self.control
+    }
+
+    pub fn vf_migration_status(&self) -> bool {
+        (self.status & 0x0001) == 0x0001
+    }
+
+    pub fn initial_vfs(&self) -> u16 {
+        self.initial_vfs
+    }
+
+    pub fn total_vfs(&self) -> u16 {
+        self.total_vfs
+    }
+
+    pub fn num_vfs(&self) -> u16 {
+        self.num_vfs
+    }
+
+    pub fn function_dependency_link(&self) -> u8 {
+        self.function_dependency_link
+    }
+
+    pub fn first_vf_offset(&self) -> u16 {
+        self.first_vf_offset
+    }
+
+    pub fn vf_stride(&self) -> u16 {
+        self.vf_stride
+    }
+
+    pub fn vf_device_id(&self) -> u16 {
+        self.vf_device_id
+    }
+
+    // Bit n set means a page size of 2^(n + 12) bytes is supported.
+    pub fn supported_page_sizes(&self) -> u32 {
+        self.supported_page_sizes
+    }
+
+    pub fn system_page_size(&self) -> u32 {
+        self.system_page_size
+    }
+
+    pub fn vf_migration_state(&self) -> u32 {
+        self.vf_migration_state
+    }
+
+    // Each VF BAR is the base of VF 1's region; VF n is found at (n - 1) * size above it.
+    pub fn vf_base_address(&self, index: u8) -> Option<PciBaseAddress> {
+        base_address(&self.vf_bars, index)
+    }
+
+    pub fn vf_bars(&self) -> Vec<PciBaseAddress> {
+        (0..6).filter_map(|i| self.vf_base_address(i)).collect()
+    }
+
+    // Routing IDs that would land above bus 255 do not exist and are left out.
+    pub fn vf_routing_ids(&self, physfn: PciAddress) -> Vec<u16> {
+        let first = physfn.routing_id() as u32 + self.first_vf_offset as u32;
+        (0..self.num_vfs as u32)
+            .map(|n| first + n * self.vf_stride as u32)
+            .filter(|rid| *rid <= u16::MAX as u32)
+            .map(|rid| rid as u16)
+            .collect()
+    }
+
+    pub fn vf_addresses(&self, physfn: PciAddress) -> Vec<PciAddress> {
+        self.vf_routing_ids(physfn)
+            .into_iter()
+            .map(|rid| PciAddress::from_routing_id(physfn.domain(), rid))
+            .collect()
+    }
+
+    pub fn set_num_vfs(&self, num_vfs: u16) -> Result<(), error::Error> {
+        self.raw.write16(OFFSET_NUM_VFS, num_vfs)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SrIovCapabilities(u32);
+
+impl SrIovCapabilities {
+    pub fn vf_migration_capable(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn ari_capable_hierarchy_preserved(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn vf_10bit_tag_requester_supported(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn vf_migration_interrupt_message_number(&self) -> u16 {
+        (self.0 >> 21) as u16
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u32 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SrIovControl(u16);
+
+impl SrIovControl {
+    pub fn vf_enable(&self) -> bool {
+        self.get_bool(0)
+    }
+
+    pub fn vf_migration_enable(&self) -> bool {
+        self.get_bool(1)
+    }
+
+    pub fn vf_migration_interrupt_enable(&self) -> bool {
+        self.get_bool(2)
+    }
+
+    pub fn vf_memory_space_enable(&self) -> bool {
+        self.get_bool(3)
+    }
+
+    pub fn ari_capable_hierarchy(&self) -> bool {
+        self.get_bool(4)
+    }
+
+    pub fn vf_10bit_tag_requester_enable(&self) -> bool {
+        self.get_bool(5)
+    }
+
+    fn get_bool(&self, bit: u8) -> bool {
+        let mask: u16 = 1 << bit;
+        (self.0 & mask) == mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::PciConfig;
+    use crate::capability::ExtendedCapability;
+    use crate::memory::Memory;
+    use crate::testing::{config, function_with_ext_cap};
+
+    fn physfn(num_vfs: u16, offset: u16, stride: u16) -> PciConfig<Memory> {
+        let mut data = function_with_ext_cap(0x0010, 1, &[]);
+        data[0x108..0x10A].copy_from_slice(&0x0019u16.to_le_bytes());
+        data[0x10C..0x10E].copy_from_slice(&64u16.to_le_bytes());
+        data[0x10E..0x110].copy_from_slice(&64u16.to_le_bytes());
+        data[0x110..0x112].copy_from_slice(&num_vfs.to_le_bytes());
+        data[0x114..0x116].copy_from_slice(&offset.to_le_bytes());
+        data[0x116..0x118].copy_from_slice(&stride.to_le_bytes());
+        data[0x11A..0x11C].copy_from_slice(&0x1520u16.to_le_bytes());
+        data[0x11C..0x120].copy_from_slice(&0x0000_0553u32.to_le_bytes());
+        data[0x120..0x124].copy_from_slice(&0x0000_0001u32.to_le_bytes());
+        data[0x124..0x128].copy_from_slice(&0xC000_000Cu32.to_le_bytes());
+        data[0x128..0x12C].copy_from_slice(&0x0000_0038u32.to_le_bytes());
+        data[0x130..0x134].copy_from_slice(&0xC010_000Cu32.to_le_bytes());
+        data[0x134..0x138].copy_from_slice(&0x0000_0038u32.to_le_bytes());
+        config(&data)
+    }
+
+    #[test]
+    fn virtual_functions() {
+        let cfg = physfn(4, 0x80, 2);
+        let Some(ExtendedCapability::SrIov(sriov)) =
+            cfg.extended_capabilities().next().map(|c| c.decode())
+        else {
+            panic!("expected an SR-IOV capability");
+        };
+
+        assert!(sriov.control().vf_enable());
+        assert!(sriov.control().vf_memory_space_enable());
+        assert!(sriov.control().ari_capable_hierarchy());
+        assert_eq!(sriov.total_vfs(), 64);
+        assert_eq!(sriov.initial_vfs(), 64);
+        assert_eq!(sriov.num_vfs(), 4);
+        assert_eq!(sriov.first_vf_offset(), 0x80);
+        assert_eq!(sriov.vf_stride(), 2);
+        assert_eq!(sriov.vf_device_id(), 0x1520);
+        assert_eq!(sriov.supported_page_sizes(), 0x553);
+        assert_eq!(sriov.system_page_size(), 1);
+
+        let bars = sriov.vf_bars();
+        assert_eq!(bars.len(), 4);
+        assert_eq!(bars[0].bar(), 0x38_C000_0000);
+        assert!(sriov.vf_base_address(1).is_none());
+        assert_eq!(sriov.vf_base_address(3).unwrap().bar(), 0x38_C010_0000);
+
+        let pf = "81:00.1".parse().unwrap();
+        assert_eq!(
+            sriov.vf_routing_ids(pf),
+            vec![0x8181, 0x8183, 0x8185, 0x8187]
+        );
+        let vfs: Vec<String> = sriov
+            .vf_addresses(pf)
+            .iter()
+            .map(|a| format!("{a:#}"))
+            .collect();
+        assert_eq!(vfs, vec!["81:10.1", "81:10.3", "81:10.5", "81:10.7"]);
+
+        // The last VFs would sit past bus 0xFF.
+        let cfg = physfn(4, 0x100, 0x100);
+        let Some(ExtendedCapability::SrIov(sriov)) =
+            cfg.extended_capabilities().next().map(|c| c.decode())
+        else {
+            panic!("expected an SR-IOV capability");
+        };
+        assert_eq!(
+            sriov.vf_routing_ids("fd:00.0".parse().unwrap()),
+            vec![0xFE00, 0xFF00]
+        );
+    }
+}
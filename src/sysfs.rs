@@ -0,0 +1,168 @@
+use super::error;
+use super::{CONFIG_SPACE_SIZE, EXTENDED_CONFIG_SPACE_SIZE, Method, PciAddress};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+const SYSFS_ROOT: &str = "/sys/bus/pci/devices";
+
+pub fn support() -> bool {
+    Path::new(SYSFS_ROOT).exists()
+}
+
+#[derive(Clone, Debug)]
+pub struct Sysfs {
+    path: PathBuf,
+    file: Option<Rc<File>>,
+    size: u16,
+}
+
+impl Method for Sysfs {
+    fn try_from(address: PciAddress) -> Result<Self, error::Error> {
+        Sysfs::open(SYSFS_ROOT, address)
+    }
+
+    fn config_size(&self) -> u16 {
+        self.size
+    }
+
+    fn read8(&self, offset: u16) -> u8 {
+        let mut buf = [0u8; 1];
+        self.read(offset, &mut buf);
+        buf[0]
+    }
+
+    fn read16(&self, offset: u16) -> u16 {
+        let mut buf = [0u8; 2];
+        self.read(offset, &mut buf);
+        u16::from_le_bytes(buf)
+    }
+
+    fn read32(&self, offset: u16) -> u32 {
+        let mut buf = [0u8; 4];
+        self.read(offset, &mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn write8(&self, offset: u16, value: u8) -> Result<(), error::Error> {
+        self.write(offset, &value.to_le_bytes())
+    }
+
+    fn write16(&self, offset: u16, value: u16) -> Result<(), error::Error> {
+        self.write(offset, &value.to_le_bytes())
+    }
+
+    fn write32(&self, offset: u16, value: u32) -> Result<(), error::Error> {
+        self.write(offset, &value.to_le_bytes())
+    }
+
+    fn read_rom(&self) -> Result<Vec<u8>, error::Error> {
+        // The kernel only hands out the ROM while "1" is written to the file.
+        let path = self.path.join("rom");
+        OpenOptions::new()
+            .write(true)
+            .open(&path)?
+            .write_all(b"1")?;
+        let rom = fs::read(&path);
+        OpenOptions::new()
+            .write(true)
+            .open(&path)?
+            .write_all(b"0")?;
+        Ok(rom?)
+    }
+}
+
+impl Sysfs {
+    pub fn open<P: AsRef<Path>>(root: P, address: PciAddress) -> Result<Self, error::Error> {
+        let path = root.as_ref().join(address.to_string());
+
+        let file = match File::open(path.join("config")) {
+            Ok(file) => Some(Rc::new(file)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(error::Error::Io(e)),
+        };
+
+        // The kernel sizes the file to 4096 bytes if it can access the extended space.
+        let size = match &file {
+            Some(file) if file.metadata()?.len() >= EXTENDED_CONFIG_SPACE_SIZE as u64 => {
+                EXTENDED_CONFIG_SPACE_SIZE
+            }
+            _ => CONFIG_SPACE_SIZE,
+        };
+
+        Ok(Sysfs { path, file, size })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    fn read(&self, offset: u16, buf: &mut [u8]) {
+        match &self.file {
+            Some(_) if offset as usize + buf.len() > self.size as usize => buf.fill(0xFF),
+            Some(file) => {
+                // Unprivileged users can read only the first 64 bytes,
+                // so the bytes which are not returned are left zero.
+                let mut pos = 0;
+                while pos < buf.len() {
+                    match file.read_at(&mut buf[pos..], offset as u64 + pos as u64) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => pos += n,
+                    }
+                }
+            }
+            None => buf.fill(0xFF),
+        }
+    }
+
+    fn write(&self, offset: u16, buf: &[u8]) -> Result<(), error::Error> {
+        if offset as usize + buf.len() > self.size as usize {
+            return Err(error::Error::OutOfRange(offset));
+        }
+
+        let file = OpenOptions::new()
+            .write(true)
+            .open(self.path.join("config"))?;
+        file.write_all_at(buf, offset as u64)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn read_fixture() {
+        let root = env::temp_dir().join(format!("pci-sysfs-{}", std::process::id()));
+        let dir = root.join("0000:00:1f.3");
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut config = vec![0u8; 64];
+        config[0..4].copy_from_slice(&[0x86, 0x80, 0x70, 0xA1]);
+        config[0x0E] = 0x80;
+        fs::write(dir.join("config"), &config).unwrap();
+
+        let present = Sysfs::open(&root, "00:1f.3".parse().unwrap()).unwrap();
+        assert_eq!(present.read16(0x00), 0x8086);
+        assert_eq!(present.read16(0x02), 0xA170);
+        assert_eq!(present.read32(0x00), 0xA170_8086);
+        assert_eq!(present.read8(0x0E), 0x80);
+        assert_eq!(present.read32(0x40), 0);
+        assert_eq!(present.config_size(), CONFIG_SPACE_SIZE);
+        assert_eq!(present.read32(0x100), 0xFFFF_FFFF);
+
+        present.write16(0x04, 0x0406).unwrap();
+        assert_eq!(present.read16(0x04), 0x0406);
+
+        let absent = Sysfs::open(&root, "00:1f.4".parse().unwrap()).unwrap();
+        assert_eq!(absent.read16(0x00), 0xFFFF);
+        assert!(absent.write16(0x04, 0x0406).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+use super::memory::Memory;
+use super::{PciConfig, get_pci_config};
+
+// A type 0 function whose only capability sits at 0x40, with `body` following the
+// ID and next pointer.
+pub(crate) fn function_with_cap(id: u8, body: &[u8]) -> Vec<u8> {
+    let mut data = vec![0u8; 256];
+    data[0x00..0x04].copy_from_slice(&[0x86, 0x80, 0xF3, 0x10]);
+    data[0x06] = 0x10;
+    data[0x34] = 0x40;
+    data[0x40] = id;
+    data[0x42..0x42 + body.len()].copy_from_slice(body);
+    data
+}
+
+// A PCI Express endpoint whose only extended capability sits at 0x100, with `body`
+// following the header.
+pub(crate) fn function_with_ext_cap(id: u16, version: u8, body: &[u8]) -> Vec<u8> {
+    let mut data = function_with_cap(0x10, &[0x02, 0x00]);
+    data.resize(4096, 0);
+    let header = id as u32 | (version as u32) << 16;
+    data[0x100..0x104].copy_from_slice(&header.to_le_bytes());
+    data[0x104..0x104 + body.len()].copy_from_slice(body);
+    data
+}
+
+// A function with the given header type whose secondary and subordinate bus, if it is a
+// bridge, are both `secondary_bus`.
+pub(crate) fn bridge(vendor_id: u16, header_type: u8, secondary_bus: u8) -> Vec<u8> {
+    let mut data = vec![0u8; 4096];
+    data[0x00..0x02].copy_from_slice(&vendor_id.to_le_bytes());
+    data[0x02..0x04].copy_from_slice(&[0x01, 0x00]);
+    data[0x0E] = header_type;
+    data[0x19] = secondary_bus;
+    data[0x1A] = secondary_bus;
+    data
+}
+
+// `port_type` adds a PCIe capability and `acs` an ACS capability with that control value.
+pub(crate) fn port(
+    header_type: u8,
+    secondary_bus: u8,
+    port_type: Option<u8>,
+    acs: Option<u16>,
+) -> Vec<u8> {
+    let mut data = bridge(0x8086, header_type, secondary_bus);
+    if let Some(port_type) = port_type {
+        data[0x06] = 0x10;
+        data[0x34] = 0x40;
+        data[0x40] = 0x10;
+        data[0x42..0x44].copy_from_slice(&(0x0002u16 | (port_type as u16) << 4).to_le_bytes());
+    }
+    if let Some(control) = acs {
+        data[0x100..0x104].copy_from_slice(&0x0001_000Du32.to_le_bytes());
+        data[0x104..0x106].copy_from_slice(&0x001Fu16.to_le_bytes());
+        data[0x106..0x108].copy_from_slice(&control.to_le_bytes());
+    }
+    data
+}
+
+pub(crate) fn config(data: &[u8]) -> PciConfig<Memory> {
+    get_pci_config(Memory::new(data).unwrap()).unwrap()
+}
+
+pub(crate) fn config_with_cap(id: u8, body: &[u8]) -> PciConfig<Memory> {
+    config(&function_with_cap(id, body))
+}
+
+pub(crate) fn config_with_ext_cap(id: u16, version: u8, body: &[u8]) -> PciConfig<Memory> {
+    config(&function_with_ext_cap(id, version, body))
+}